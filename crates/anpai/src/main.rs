@@ -4,12 +4,103 @@ use feel::eval;
 use feel::parse as feel_parse;
 
 use dmn::eval as dmn_eval;
+use dmn::parse as dmn_parse;
 use dmn::types::DmnError;
 
 use fileinput::FileInput;
 use std::fs::File;
 use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::IsTerminal;
 use std::io::Read;
+use std::io::Write;
+use std::time::Instant;
+
+// start timing only when measuring so there is no cost otherwise
+fn start_measure(measure: bool) -> Option<Instant> {
+    if measure {
+        Some(Instant::now())
+    } else {
+        None
+    }
+}
+
+fn end_measure(start: Option<Instant>, stage: &str) {
+    if let Some(start) = start {
+        eprintln!("{} time: {:?}", stage, start.elapsed());
+    }
+}
+
+// read the context file given by --varsfile, the error message
+// carries the path so it can be printed as is
+fn read_context_file(path: &str) -> Result<String, String> {
+    File::open(path)
+        .and_then(read_input)
+        .map_err(|err| format!("cannot read context file: {}: {}", path, err))
+}
+
+// read the optional context file, print the error and exit non-zero
+// when it cannot be read
+fn read_varsfile_or_exit(varsfile: &Option<String>) -> Option<String> {
+    varsfile.as_ref().map(|path| match read_context_file(path) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    })
+}
+
+// the engine for the commands, --strict switches to the DMN 1.2
+// dialect
+fn new_engine(strict: bool) -> Box<eval::Engine> {
+    let mut eng = Box::new(eval::Engine::new());
+    if strict {
+        eng.set_dialect(eval::Dialect::Dmn12);
+    }
+    eng
+}
+
+// print the trace events of the engine to stderr
+fn trace_to_stderr(eng: &mut eval::Engine) {
+    eng.set_trace(true);
+    eng.set_event_hook(Box::new(|event| {
+        if let eval::EngineEvent::Trace(kind, node, pos) = event {
+            eprintln!("trace: {} {} at {}", kind, node, pos);
+        }
+    }));
+}
+
+// the flags controlling how the commands evaluate and print
+#[derive(Debug, Default, Clone, Copy)]
+struct EvalOptions {
+    // dump the AST instead of evaluating, feel only
+    dump_ast: bool,
+    // print the AST or the result in JSON, feel only
+    json: bool,
+    // print the parse and eval time to stderr
+    measure: bool,
+    // print each node to stderr before evaluating it
+    trace: bool,
+    // follow the DMN 1.2 specification
+    strict: bool,
+}
+
+// serialize to pretty JSON, a failure is reported as an eval error
+// instead of panicking
+fn to_json_pretty<T: serde::Serialize>(value: &T) -> Result<String, eval::EvalError> {
+    serde_json::to_string_pretty(value).map_err(|err| {
+        eval::EvalError::runtime(format!("cannot serialize to JSON: {}", err).as_str())
+    })
+}
+
+// read all contents from the reader, a clean EOF gives an empty string
+fn read_input<R: Read>(reader: R) -> std::io::Result<String> {
+    let mut reader = BufReader::new(reader);
+    let mut buf: String = String::new();
+    reader.read_to_string(&mut buf)?;
+    Ok(buf)
+}
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -23,7 +114,10 @@ enum AnpaiCommands {
         #[arg(long, help = "Dump AST node instead of evaluating")]
         ast: bool,
 
-        #[arg(long, help = "Output format is JSON")]
+        #[arg(long, help = "Dump the scanned tokens instead of parsing")]
+        dump_tokens: bool,
+
+        #[arg(long, help = "Output AST or result in JSON format")]
         json: bool,
 
         #[arg(long, help = "Context variable file")]
@@ -38,6 +132,18 @@ enum AnpaiCommands {
         #[arg(short, long, help = "Parse top mode")]
         top: Option<feel_parse::ParseTop>,
 
+        #[arg(long, help = "Print parse and eval time to stderr")]
+        measure: bool,
+
+        #[arg(long, help = "Print each node to stderr before evaluating it")]
+        trace: bool,
+
+        #[arg(
+            long,
+            help = "Follow the DMN 1.2 specification instead of the loose defaults"
+        )]
+        strict: bool,
+
         files: Vec<String>,
     },
 
@@ -52,6 +158,24 @@ enum AnpaiCommands {
         #[arg(long, short = 's', help = "Start decision id")]
         start_decision_id: Option<String>,
 
+        #[arg(
+            long,
+            help = "List the decision ids, names and required inputs instead of evaluating"
+        )]
+        list_decisions: bool,
+
+        #[arg(long, help = "Print parse and eval time to stderr")]
+        measure: bool,
+
+        #[arg(long, help = "Print each node to stderr before evaluating it")]
+        trace: bool,
+
+        #[arg(
+            long,
+            help = "Follow the DMN 1.2 specification instead of the loose defaults"
+        )]
+        strict: bool,
+
         file: String,
     },
 }
@@ -60,18 +184,14 @@ impl AnpaiCommands {
     fn parse_and_eval_feel(
         &self,
         code: &str,
-        varsfile: Option<String>,
+        varsfile_content: Option<String>,
         vars: Option<String>,
         top: Option<feel_parse::ParseTop>,
-        dump_ast: bool,
-        json_format: bool,
+        opts: EvalOptions,
     ) -> Result<(), eval::EvalError> {
-        let mut eng = Box::new(eval::Engine::new());
+        let mut eng = new_engine(opts.strict);
         // read context vars
-        if let Some(context_varsfile) = varsfile {
-            let mut data_file = File::open(context_varsfile.as_str()).unwrap();
-            let mut content = String::new();
-            data_file.read_to_string(&mut content).unwrap();
+        if let Some(content) = varsfile_content {
             eng.load_context_string(&content)?;
         }
 
@@ -79,35 +199,52 @@ impl AnpaiCommands {
             eng.load_context_string(&context_vars)?;
         }
 
+        let parse_start = start_measure(opts.measure);
         let n = feel_parse::parse(code, eng.clone(), top.unwrap_or_default())?;
+        end_measure(parse_start, "parse");
 
-        if dump_ast {
-            if json_format {
-                let serialized = serde_json::to_string_pretty(&n).unwrap();
+        if opts.dump_ast {
+            if opts.json {
+                let serialized = to_json_pretty(&n)?;
                 println!("{}", serialized);
             } else {
                 println!("{}", n);
             }
         } else {
+            if opts.trace {
+                trace_to_stderr(&mut eng);
+            }
+            let eval_start = start_measure(opts.measure);
             let res = eng.eval(n.clone())?;
-            println!("{}", res);
+            end_measure(eval_start, "eval");
+            if opts.json {
+                // stream the result instead of building a string
+                let mut writer = BufWriter::new(std::io::stdout().lock());
+                if let Err(err) = res
+                    .write_json(&mut writer)
+                    .and_then(|_| writeln!(writer))
+                    .and_then(|_| writer.flush())
+                {
+                    return Err(eval::EvalError::runtime(err.to_string().as_str()));
+                }
+            } else {
+                println!("{}", res);
+            }
         }
         Ok(())
     }
 
     fn parse_and_eval_dmn(
         &self,
-        varsfile: Option<String>,
+        varsfile_content: Option<String>,
         vars: Option<String>,
         start_decision_id: Option<String>,
         file: String,
+        opts: EvalOptions,
     ) -> Result<(), DmnError> {
-        let mut eng = Box::new(eval::Engine::new());
+        let mut eng = new_engine(opts.strict);
         // read context vars
-        if let Some(context_varsfile) = varsfile {
-            let mut data_file = File::open(context_varsfile.as_str()).unwrap();
-            let mut content = String::new();
-            data_file.read_to_string(&mut content).unwrap();
+        if let Some(content) = varsfile_content {
             match eng.load_context_string(&content) {
                 Ok(_) => (),
                 Err(err) => {
@@ -130,43 +267,101 @@ impl AnpaiCommands {
             }
         }
 
-        //dmn_parse::parse_file(file.as_str());
-        let v = dmn_eval::eval_file(&mut eng, file.as_str(), start_decision_id)?;
+        let parse_start = start_measure(opts.measure);
+        let diagram = dmn_parse::Parser::new().parse_file(file.as_str())?;
+        end_measure(parse_start, "parse");
+
+        if opts.trace {
+            trace_to_stderr(&mut eng);
+        }
+        let eval_start = start_measure(opts.measure);
+        let v = dmn_eval::eval_dmn_diagram(&mut eng, &diagram, start_decision_id)?;
+        end_measure(eval_start, "eval");
         println!("{}", v);
         Ok(())
     }
 
+    // print one decision per line as tab separated start decision id,
+    // name and the required input names
+    fn list_dmn_decisions(&self, file: &str) -> Result<(), DmnError> {
+        for decision in dmn_parse::Parser::new().list_decisions(file)? {
+            println!(
+                "#{}\t{}\t{}",
+                decision.id,
+                decision.name,
+                decision.required_inputs.join(", ")
+            );
+        }
+        Ok(())
+    }
+
     fn execute(&self) -> () {
         match self {
             Self::Feel {
                 ast,
+                dump_tokens,
                 json,
                 varsfile,
                 vars,
                 top,
                 code,
                 files,
+                measure,
+                trace,
+                strict,
             } => {
                 let input = if let Some(code) = code.clone() {
                     //self.parse_and_eval(code.as_str())
                     code
                 } else {
+                    if files.is_empty() && std::io::stdin().is_terminal() {
+                        eprintln!(
+                            "reading FEEL code from stdin, press Ctrl-D to finish, or pass --code/files instead"
+                        );
+                    }
                     let filenames: Vec<&str> = files.iter().map(|s| s.as_str()).collect();
                     let fileinput = FileInput::new(&filenames);
-                    let mut reader = BufReader::new(fileinput);
 
                     // read all contents from either files or stdin
-                    let mut buf: String = String::new();
-                    reader.read_to_string(&mut buf).unwrap();
-                    buf
+                    match read_input(fileinput) {
+                        Ok(buf) => buf,
+                        Err(err) => {
+                            eprintln!("Error reading input: {}", err);
+                            std::process::exit(1);
+                        }
+                    }
                 };
+                if input.trim().is_empty() {
+                    // nothing to evaluate
+                    return;
+                }
+                if *dump_tokens {
+                    match feel::scan::Scanner::new(input.as_str()).find_tokens() {
+                        Ok(tokens) => {
+                            for token in tokens {
+                                println!("{}", token);
+                            }
+                        }
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            std::process::exit(1);
+                        }
+                    }
+                    return;
+                }
+                let varsfile_content = read_varsfile_or_exit(varsfile);
                 match self.parse_and_eval_feel(
                     input.as_str(),
-                    varsfile.clone(),
+                    varsfile_content,
                     vars.clone(),
                     top.clone(),
-                    *ast,
-                    *json,
+                    EvalOptions {
+                        dump_ast: *ast,
+                        json: *json,
+                        measure: *measure,
+                        trace: *trace,
+                        strict: *strict,
+                    },
                 ) {
                     Ok(_) => (),
 
@@ -177,19 +372,40 @@ impl AnpaiCommands {
                             err.pos,
                             err.pos.line_pointers(input.as_str())
                         );
+                        std::process::exit(1);
                     }
                 }
             }
+            Self::Dmn {
+                list_decisions: true,
+                file,
+                ..
+            } => {
+                if let Err(err) = self.list_dmn_decisions(file) {
+                    eprintln!("Error {}", err);
+                    std::process::exit(1);
+                }
+            }
             Self::Dmn {
                 varsfile,
                 vars,
                 start_decision_id,
+                list_decisions: _,
                 file,
+                measure,
+                trace,
+                strict,
             } => match self.parse_and_eval_dmn(
-                varsfile.clone(),
+                read_varsfile_or_exit(varsfile),
                 vars.clone(),
                 start_decision_id.clone(),
                 file.clone(),
+                EvalOptions {
+                    measure: *measure,
+                    trace: *trace,
+                    strict: *strict,
+                    ..Default::default()
+                },
             ) {
                 Ok(_) => (),
                 Err(DmnError::FEELEval(err, path, code)) => {
@@ -200,9 +416,11 @@ impl AnpaiCommands {
                         err.pos,
                         err.pos.line_pointers(code.as_str()),
                     );
+                    std::process::exit(1);
                 }
                 Err(err) => {
                     eprintln!("Error {}", err);
+                    std::process::exit(1);
                 }
             },
         }
@@ -215,3 +433,33 @@ fn main() {
     let args = AnpaiCommands::parse();
     args.execute()
 }
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_read_empty_input() {
+        let input = super::read_input(std::io::empty()).unwrap();
+        assert_eq!(input, "");
+
+        let input = super::read_input("1 + 2\n".as_bytes()).unwrap();
+        assert_eq!(input, "1 + 2\n");
+    }
+
+    struct Unserializable;
+
+    impl serde::Serialize for Unserializable {
+        fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("unsupported node"))
+        }
+    }
+
+    #[test]
+    fn test_to_json_pretty_error() {
+        let err = super::to_json_pretty(&Unserializable).unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "RuntimeError: cannot serialize to JSON: unsupported node"
+        );
+        assert_eq!(super::to_json_pretty(&vec![1]).unwrap(), "[\n  1\n]");
+    }
+}
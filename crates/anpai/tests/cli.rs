@@ -0,0 +1,177 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_missing_varsfile() {
+    for args in [
+        vec!["feel", "--varsfile", "no-such-vars.json", "-c", "1 + 2"],
+        vec![
+            "dmn",
+            "--varsfile",
+            "no-such-vars.json",
+            "../../examples/dmn/simpledish.dmn",
+        ],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+            .args(&args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.starts_with("cannot read context file: no-such-vars.json: "),
+            "unexpected stderr {}",
+            stderr
+        );
+        assert!(!stderr.contains("panicked"));
+    }
+}
+
+#[test]
+fn test_feel_dump_tokens() {
+    let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args(["feel", "--dump-tokens", "-c", r#"a + "b""#])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "[0..1] name 'a'\n[2..3] + '+'\n[4..7] string '\"b\"'\n"
+    );
+}
+
+#[test]
+fn test_feel_strict() {
+    let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args(["feel", "-c", r#"to boolean("yes")"#])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "true\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args(["feel", "--strict", "-c", r#"to boolean("yes")"#])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with("TypeError: "),
+        "unexpected stderr {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_dmn_list_decisions() {
+    let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args([
+            "dmn",
+            "--list-decisions",
+            "../../examples/dmn/simpledish.dmn",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "#Decision_0rwi1k0\tDish\tSeason, Number of Guests\n\
+         #Decision_0ggmobg\tBeverages\tGuests with children?\n"
+    );
+
+    // the listed id is accepted as the start decision
+    let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args([
+            "dmn",
+            "-s",
+            "#Decision_0rwi1k0",
+            "--vars",
+            r#"{"season": "Fall", "guestCount": 4}"#,
+            "../../examples/dmn/simpledish.dmn",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.stderr.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"Dish\":\"Sparelibs\"}\n"
+    );
+}
+
+#[test]
+fn test_dmn_error_exit_code() {
+    let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args(["dmn", "-s", "#nope", "../../examples/dmn/simpledish.dmn"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error no element `decision[@id=#nope]`\n"
+    );
+}
+
+#[test]
+fn test_feel_empty_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args(["feel"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // close the stdin without writing anything
+    drop(child.stdin.take());
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // a piped expression is still evaluated
+    let mut child = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args(["feel"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"1 + 2").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+}
+
+#[test]
+fn test_feel_unreadable_file() {
+    let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args(["feel", "no-such-file.feel"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Error reading input: "));
+}
+
+#[test]
+fn test_feel_var_named_mod() {
+    let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args(["feel", "--vars", r#"{"mod": 3}"#, "-c", "mod + 1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "4\n");
+}
+
+#[test]
+fn test_feel_trace() {
+    let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args(["feel", "--trace", "-c", "1 + 2"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 3, "unexpected stderr {}", stderr);
+    assert!(lines[0].starts_with("trace: BinOp (+ 1 2) at "));
+    assert!(lines[1].starts_with("trace: Number 1 at "));
+    assert!(lines[2].starts_with("trace: Number 2 at "));
+}
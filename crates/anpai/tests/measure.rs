@@ -0,0 +1,27 @@
+use std::process::Command;
+
+#[test]
+fn test_feel_measure() {
+    let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args(["feel", "--measure", "-c", "1 + 2"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 2, "unexpected stderr {}", stderr);
+    assert!(lines[0].starts_with("parse time: "));
+    assert!(lines[1].starts_with("eval time: "));
+}
+
+#[test]
+fn test_feel_without_measure() {
+    let output = Command::new(env!("CARGO_BIN_EXE_anpai"))
+        .args(["feel", "-c", "1 + 2"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+    assert!(output.stderr.is_empty());
+}
@@ -121,7 +121,8 @@ impl XMLQuery<'_> {
             let value = xpath.evaluate(&self.context, node)?;
             match value {
                 Value::Nodeset(nodeset) => {
-                    for n in nodeset.iter() {
+                    // a nodeset is unordered, keep the elements as in the document
+                    for n in nodeset.document_order() {
                         if let Node::Element(_) = n {
                             nodes.push(n.clone());
                         }
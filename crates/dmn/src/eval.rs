@@ -1,6 +1,6 @@
 use crate::parse::Parser;
-use crate::types::{Decision, Diagram, DmnError, Rule};
-use feel::eval::Engine;
+use crate::types::{Decision, Diagram, DmnError, InputTypeMismatch, Rule};
+use feel::eval::{Engine, EvalError};
 use feel::values::context::Context;
 use feel::values::value::Value;
 use std::cell::RefCell;
@@ -27,30 +27,234 @@ fn rule_matched(rule: &Rule, engine: &mut Box<Engine>, input_values: &Vec<Value>
     return true;
 }
 
+// map a DMN typeRef to the FEEL data type and the function
+// converting a string to that type
+fn type_ref_conversion(type_ref: &str) -> Option<(&'static str, &'static str)> {
+    match type_ref {
+        "number" | "integer" | "long" | "double" => Some(("number", "number")),
+        "string" => Some(("string", "string")),
+        "boolean" => Some(("boolean", "")),
+        "date" => Some(("date", "date")),
+        "time" => Some(("time", "time")),
+        "dateTime" | "date and time" => Some(("date time", "date and time")),
+        "duration"
+        | "dayTimeDuration"
+        | "yearMonthDuration"
+        | "days and time duration"
+        | "years and months duration" => Some(("duration", "duration")),
+        _ => None,
+    }
+}
+
+/// coerce the value to the declared typeRef, e.g. string "5" to
+/// number 5 when the typeRef is number. an empty or unknown typeRef
+/// keeps the value as it is
+pub fn coerce_type_ref(
+    engine: &mut Box<Engine>,
+    value: Value,
+    type_ref: &str,
+) -> Result<Value, EvalError> {
+    let Some((data_type, conv_func)) = type_ref_conversion(type_ref) else {
+        return Ok(value);
+    };
+    if value == Value::NullV || value.data_type() == data_type {
+        return Ok(value);
+    }
+    match (data_type, &value) {
+        ("boolean", Value::StrV(s)) => match s.as_str() {
+            "true" => Ok(Value::BoolV(true)),
+            "false" => Ok(Value::BoolV(false)),
+            _ => Err(EvalError::type_error(
+                format!("{}, cannot coerce string {} to boolean", type_ref, value).as_str(),
+            )),
+        },
+        ("string", Value::NumberV(_)) | ("string", Value::BoolV(_)) | (_, Value::StrV(_)) => {
+            let func = engine
+                .resolve(conv_func.to_owned())
+                .ok_or(EvalError::runtime(
+                    format!("{}, no conversion function `{}`", type_ref, conv_func).as_str(),
+                ))?;
+            engine.call_function(&func, vec![value])
+        }
+        _ => Err(EvalError::type_error(
+            format!(
+                "{}, cannot coerce {} to {}",
+                type_ref,
+                value.data_type(),
+                data_type
+            )
+            .as_str(),
+        )),
+    }
+}
+
+/// check the value matches the declared typeRef
+fn validate_type_ref(value: &Value, type_ref: &str) -> Result<(), EvalError> {
+    match type_ref_conversion(type_ref) {
+        Some((data_type, _)) if *value != Value::NullV && value.data_type() != data_type => Err(
+            EvalError::type_error(format!("{}, found {}", type_ref, value.data_type()).as_str()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// evaluate the text of an output entry, the entries of the context,
+/// e.g. the matched input values, are visible besides the variables
+/// of the engine
+pub fn eval_output_entry(
+    engine: &mut Box<Engine>,
+    text: &str,
+    context: &Context,
+) -> Result<Value, EvalError> {
+    engine.load_context(context.entries());
+    let res = engine.parse_and_eval(text);
+    engine.pop_frame();
+    res
+}
+
+// depth first visit of the required decisions, a decision is pushed
+// to the order after all of its requirements
+fn visit_decision(
+    diagram: &Diagram,
+    decision: Decision,
+    visiting: &mut Vec<Decision>,
+    order: &mut Vec<Decision>,
+) -> Result<(), DmnError> {
+    if order.iter().any(|d| d.id == decision.id) {
+        return Ok(());
+    }
+    if let Some(pos) = visiting.iter().position(|d| d.id == decision.id) {
+        let mut names: Vec<String> = visiting[pos..].iter().map(|d| d.result_name()).collect();
+        names.push(decision.result_name());
+        return Err(DmnError::CircularDependency(names));
+    }
+    visiting.push(decision.clone());
+    for decision_id in decision.requirements.required_decisions.iter() {
+        let required = diagram.find_decision(decision_id.clone())?;
+        visit_decision(diagram, required, visiting, order)?;
+    }
+    visiting.pop();
+    order.push(decision);
+    Ok(())
+}
+
+/// the decisions to evaluate for the given decision, the required
+/// decisions come first and the decision itself is the last one. a
+/// decision required by several others appears only once
+pub fn decision_order(diagram: &Diagram, decision: Decision) -> Result<Vec<Decision>, DmnError> {
+    let mut order = vec![];
+    visit_decision(diagram, decision, &mut vec![], &mut order)?;
+    Ok(order)
+}
+
+/// check the variables given for the input data required by the
+/// decisions against the declared typeRef, all the mismatches are
+/// reported at once. a missing or null variable is not checked
+pub fn validate_input_data(
+    engine: &Engine,
+    diagram: &Diagram,
+    decisions: &[Decision],
+) -> Result<(), DmnError> {
+    let mut mismatches: Vec<InputTypeMismatch> = vec![];
+    let mut checked: Vec<&String> = vec![];
+    for href in decisions
+        .iter()
+        .flat_map(|d| d.requirements.required_inputs.iter())
+    {
+        if checked.contains(&href) {
+            continue;
+        }
+        checked.push(href);
+        let Some(input_data) = diagram
+            .input_datas
+            .iter()
+            .find(|input| format!("#{}", input.id) == *href)
+        else {
+            continue;
+        };
+        let Some((data_type, _)) = type_ref_conversion(input_data.type_ref.as_str()) else {
+            continue;
+        };
+        match engine.resolve(input_data.name.clone()) {
+            Some(value) if value != Value::NullV && value.data_type() != data_type => mismatches
+                .push(InputTypeMismatch {
+                    name: input_data.name.clone(),
+                    expected: input_data.type_ref.clone(),
+                    found: value.data_type(),
+                }),
+            _ => (),
+        }
+    }
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(DmnError::InputType(mismatches))
+    }
+}
+
+/// evaluate the required decisions in dependency order, then the
+/// decision itself. the result of a required decision is bound by the
+/// decision name, it is the output value when the table has a single
+/// output, otherwise the context of outputs. the outputs are bound by
+/// their own names as well
 pub fn eval_decision(
     engine: &mut Box<Engine>,
     decision: Decision,
     diagram: &Diagram,
 ) -> Result<Context, DmnError> {
-    // recursively call required decisions
-    for decision_id in decision.requirements.required_decisions.iter() {
-        let required = diagram.find_decision(decision_id.clone())?;
-        let req_context = eval_decision(engine, required, diagram)?;
-        engine.load_context(req_context.entries());
+    let mut order = decision_order(diagram, decision)?;
+    validate_input_data(engine, diagram, &order)?;
+    let decision = order.pop().unwrap();
+
+    engine.push_frame();
+    for required in order {
+        let req_context = match eval_decision_table(engine, &required) {
+            Ok(ctx) => ctx,
+            Err(err) => {
+                engine.pop_frame();
+                return Err(err);
+            }
+        };
+        let result = match req_context.entries().as_slice() {
+            [(_, v)] => v.clone(),
+            _ => Value::ContextV(Rc::new(RefCell::new(req_context.clone()))),
+        };
+        for (k, v) in req_context.entries() {
+            engine.bind_var(k, v);
+        }
+        engine.bind_var(required.result_name(), result);
     }
+    let res = eval_decision_table(engine, &decision);
+    engine.pop_frame();
+    res
+}
 
-    if let Some(table) = decision.decision_table {
+// evaluate the decision table of a single decision, the required
+// decisions are expected to be bound already
+fn eval_decision_table(engine: &mut Box<Engine>, decision: &Decision) -> Result<Context, DmnError> {
+    if let Some(table) = &decision.decision_table {
         let mut input_values: Vec<Value> = vec![];
         for (input_idx, input) in table.inputs.iter().enumerate() {
             let input_text = input.expression.text.clone();
             let path = format!("input/{}[@id={}]", input_idx, input.id);
-            let input_value = match engine.parse_and_eval(input_text.as_str()) {
+            let input_value = match engine
+                .parse_and_eval(input_text.as_str())
+                .and_then(|v| coerce_type_ref(engine, v, input.expression.type_ref.as_str()))
+            {
                 Ok(v) => v,
                 Err(err) => return Err(DmnError::FEELEval(err, path, input_text)),
             };
             input_values.push(input_value);
         }
 
+        // input values are visible to output entries by their labels
+        let mut input_context = Context::new();
+        for (input, value) in table.inputs.iter().zip(input_values.iter()) {
+            if !input.label.is_empty() {
+                input_context.insert(input.label.clone(), value.clone());
+            }
+        }
+
         for (rule_idx, rule) in table.rules.iter().enumerate() {
             if rule_matched(&rule, engine, &input_values) {
                 // render the result
@@ -65,10 +269,15 @@ pub fn eval_decision(
                         "rule/{}/outputEntry/{}[@id={}]",
                         rule_idx, i, output_entry.id
                     );
-                    let output_value = match engine.parse_and_eval(output_text.as_str()) {
-                        Ok(v) => v,
-                        Err(err) => return Err(DmnError::FEELEval(err, path, output_text)),
-                    };
+                    let output_value =
+                        match eval_output_entry(engine, output_text.as_str(), &input_context)
+                            .and_then(|v| {
+                                validate_type_ref(&v, output.type_ref.as_str())?;
+                                Ok(v)
+                            }) {
+                            Ok(v) => v,
+                            Err(err) => return Err(DmnError::FEELEval(err, path, output_text)),
+                        };
                     output_context.insert(output.name.clone(), output_value);
                 }
                 return Ok(output_context.clone());
@@ -105,3 +314,130 @@ pub fn eval_file(
     //println!("diagram {:?}", diagram);
     eval_dmn_diagram(engine, &diagram, start_decision_id)
 }
+
+#[cfg(test)]
+mod test {
+    use crate::types::{DmnError, InputTypeMismatch};
+    use feel::eval::{Engine, EvalErrorKind};
+    use feel::values::context::Context;
+    use feel::values::numeric::Numeric;
+    use feel::values::value::Value;
+
+    fn eval_coerce_dmn(vars: &str) -> Result<String, DmnError> {
+        let mut eng = Box::new(Engine::new());
+        eng.load_context_string(vars).unwrap();
+        let v = super::eval_file(&mut eng, "src/fixtures/dmn/coerce.dmn", None)?;
+        Ok(v.to_string())
+    }
+
+    #[test]
+    fn test_coerce_input_type_ref() {
+        let res = eval_coerce_dmn(r#"{amount: "5", vip: "true"}"#).unwrap();
+        assert_eq!(res, r#"{"Discount":10}"#);
+        let res = eval_coerce_dmn(r#"{amount: 50, vip: false}"#).unwrap();
+        assert_eq!(res, r#"{"Discount":5}"#);
+    }
+
+    #[test]
+    fn test_eval_output_entry() {
+        let mut eng = Box::new(Engine::new());
+        eng.load_context_string("{input: {a: 2, b: 3}}").unwrap();
+        let mut input_context = Context::new();
+        input_context.insert("A".to_owned(), Value::NumberV(Numeric::from_i32(4)));
+        input_context.insert("B".to_owned(), Value::NumberV(Numeric::from_i32(5)));
+
+        let v = super::eval_output_entry(&mut eng, "input.a + input.b", &input_context).unwrap();
+        assert_eq!(v.to_string(), "5");
+        let v = super::eval_output_entry(&mut eng, "A * B + input.a", &input_context).unwrap();
+        assert_eq!(v.to_string(), "22");
+        // the input context is not left in the engine
+        assert!(!eng.has_name("A".to_owned()));
+
+        let v = super::eval_file(&mut eng, "src/fixtures/dmn/total.dmn", None).unwrap();
+        assert_eq!(v.to_string(), r#"{"Product":6, "Total":5}"#);
+    }
+
+    #[test]
+    fn test_decision_dependency() {
+        let mut eng = Box::new(Engine::new());
+        eng.load_context_string("{x: 3}").unwrap();
+        let v = super::eval_file(&mut eng, "src/fixtures/dmn/dependency.dmn", None).unwrap();
+        // Base = 6, Middle = 7, Final = 42
+        assert_eq!(v.to_string(), r#"{"Final":42}"#);
+
+        let v = super::eval_file(
+            &mut eng,
+            "src/fixtures/dmn/dependency.dmn",
+            Some("#Decision_middle".to_owned()),
+        )
+        .unwrap();
+        assert_eq!(v.to_string(), r#"{"Middle":7}"#);
+        // the required results are not left in the engine
+        assert!(!eng.has_name("Base".to_owned()));
+
+        let diagram = crate::parse::Parser::new()
+            .parse_file("src/fixtures/dmn/dependency.dmn")
+            .unwrap();
+        let order = super::decision_order(&diagram, diagram.decisions[2].clone()).unwrap();
+        let names: Vec<String> = order.iter().map(|d| d.result_name()).collect();
+        assert_eq!(names, vec!["Base", "Middle", "Final"]);
+    }
+
+    #[test]
+    fn test_circular_dependency() {
+        let mut eng = Box::new(Engine::new());
+        let res = super::eval_file(&mut eng, "src/fixtures/dmn/cycle.dmn", None);
+        match res {
+            Err(err @ DmnError::CircularDependency(_)) => assert_eq!(
+                err.to_string(),
+                "circular decision dependency Third -> Second -> First -> Third"
+            ),
+            _ => panic!("expect circular dependency, found {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_validate_input_data() {
+        let eval_typed = |vars: &str| {
+            let mut eng = Box::new(Engine::new());
+            eng.load_context_string(vars).unwrap();
+            super::eval_file(&mut eng, "src/fixtures/dmn/typed.dmn", None)
+        };
+        let v = eval_typed(r#"{amount: 100, member: true}"#).unwrap();
+        assert_eq!(v.to_string(), r#"{"Price":90}"#);
+
+        let res = eval_typed(r#"{amount: "100", member: true}"#);
+        match res {
+            Err(DmnError::InputType(ref mismatches)) => assert_eq!(
+                mismatches,
+                &vec![InputTypeMismatch {
+                    name: "amount".to_owned(),
+                    expected: "number".to_owned(),
+                    found: "string".to_owned(),
+                }]
+            ),
+            _ => panic!("expect input type error, found {:?}", res),
+        }
+
+        let err = eval_typed(r#"{amount: "100", member: 1}"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "input data type mismatch, `amount` expect number, found string; `member` expect boolean, found number"
+        );
+    }
+
+    #[test]
+    fn test_type_ref_mismatch() {
+        let res = eval_coerce_dmn(r#"{amount: "abc", vip: true}"#);
+        assert!(matches!(res, Err(DmnError::FEELEval(_, _, _))));
+
+        // the output "bad" is not a number
+        let res = eval_coerce_dmn(r#"{amount: 500, vip: true}"#);
+        match res {
+            Err(DmnError::FEELEval(err, _, _)) => {
+                assert!(matches!(err.kind, EvalErrorKind::TypeError(_)))
+            }
+            _ => panic!("expect type error, found {:?}", res),
+        }
+    }
+}
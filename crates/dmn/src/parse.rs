@@ -190,6 +190,10 @@ impl Parser<'_> {
     fn parse_decision(&self, node: Node) -> Result<Decision, DmnError> {
         if let Node::Element(_) = node {
             let id = self.xml_query.get_attribute(node, "id")?;
+            let name = self
+                .xml_query
+                .get_attribute(node, "name")
+                .unwrap_or_default();
             let decision_table = match self
                 .xml_query
                 .get_first_element_node(node, "ns:decisionTable")
@@ -202,6 +206,7 @@ impl Parser<'_> {
             let requirements = self.parse_requirements(node)?;
             Ok(Decision {
                 id,
+                name,
                 decision_table,
                 requirements,
             })
@@ -213,10 +218,20 @@ impl Parser<'_> {
     fn parse_input_data(&self, node: Node) -> Result<InputData, DmnError> {
         let id = self.xml_query.get_attribute(node, "id")?;
         let name = self.xml_query.get_attribute(node, "name")?;
+        // the type is declared by the typeRef of the variable
+        let type_ref = match self.xml_query.get_first_element_node(node, "ns:variable") {
+            Ok(n) => self
+                .xml_query
+                .get_attribute(n, "typeRef")
+                .unwrap_or_default(),
+            Err(XmlError::NoElement(_)) => "".to_owned(),
+            Err(err) => return Err(err.into()),
+        };
         let requirements = self.parse_requirements(node)?;
         Ok(InputData {
             id,
             name,
+            type_ref,
             requirements,
         })
     }
@@ -277,6 +292,32 @@ impl Parser<'_> {
             .get_first_element_node(doc.root().into(), "ns:definitions")?;
         self.parse_diagram(node)
     }
+
+    // list the decisions of a file in document order, the required
+    // inputs are given by the input data names
+    pub fn list_decisions(&self, path: &str) -> Result<Vec<DecisionInfo>, DmnError> {
+        let diagram = self.parse_file(path)?;
+        Ok(diagram
+            .decisions
+            .iter()
+            .map(|decision| DecisionInfo {
+                id: decision.id.clone(),
+                name: decision.name.clone(),
+                required_inputs: decision
+                    .requirements
+                    .required_inputs
+                    .iter()
+                    .map(|href| {
+                        diagram
+                            .input_datas
+                            .iter()
+                            .find(|input| format!("#{}", input.id) == *href)
+                            .map_or_else(|| href.clone(), |input| input.name.clone())
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
 }
 
 pub fn parse_file(path: &str) {
@@ -292,4 +333,19 @@ mod test {
     fn test_parse_simple_dmn() {
         super::parse_file("src/fixtures/dmn/simpledish.dmn");
     }
+
+    #[test]
+    fn test_list_decisions() {
+        let decisions = super::Parser::new()
+            .list_decisions("src/fixtures/dmn/simpledish.dmn")
+            .unwrap();
+        let ids: Vec<&str> = decisions.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["Decision_0rwi1k0", "Decision_0ggmobg"]);
+        assert_eq!(decisions[0].name, "Dish");
+        assert_eq!(
+            decisions[0].required_inputs,
+            vec!["Season", "Number of Guests"]
+        );
+        assert_eq!(decisions[1].required_inputs, vec!["Guests with children?"]);
+    }
 }
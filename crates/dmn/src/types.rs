@@ -13,6 +13,8 @@ pub enum DmnError {
     IOError(String),
     XML(XmlError),
     FEELEval(FEELEvelError, String, String),
+    CircularDependency(Vec<String>),
+    InputType(Vec<InputTypeMismatch>),
 }
 impl error::Error for DmnError {}
 
@@ -37,10 +39,35 @@ impl fmt::Display for DmnError {
             Self::IOError(error_message) => write!(f, "io error {}", error_message),
             Self::XML(err) => write!(f, "parse XML error {}", err),
             Self::FEELEval(err, path, _) => write!(f, "eval FEEL error at {}, {}", path, err),
+            Self::InputType(mismatches) => {
+                let messages: Vec<String> = mismatches.iter().map(|m| m.to_string()).collect();
+                write!(f, "input data type mismatch, {}", messages.join("; "))
+            }
+            Self::CircularDependency(names) => {
+                write!(f, "circular decision dependency {}", names.join(" -> "))
+            }
         }
     }
 }
 
+// an input data variable whose value does not match the declared typeRef
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputTypeMismatch {
+    pub name: String,
+    pub expected: String,
+    pub found: String,
+}
+
+impl fmt::Display for InputTypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "`{}` expect {}, found {}",
+            self.name, self.expected, self.found
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct InputExpression {
     pub id: String,
@@ -102,6 +129,7 @@ pub struct Requirements {
 pub struct InputData {
     pub id: String,
     pub name: String,
+    pub type_ref: String,
     pub requirements: Requirements,
 }
 
@@ -122,10 +150,19 @@ pub struct KnowledgeSource {
 #[derive(Clone, Debug)]
 pub struct Decision {
     pub id: String,
+    pub name: String,
     pub decision_table: Option<DecisionTable>,
     pub requirements: Requirements,
 }
 
+// what a decision needs, listed without evaluating it
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecisionInfo {
+    pub id: String,
+    pub name: String,
+    pub required_inputs: Vec<String>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Diagram {
     pub id: String,
@@ -135,6 +172,18 @@ pub struct Diagram {
     pub knowledge_sources: Vec<KnowledgeSource>,
 }
 
+impl Decision {
+    // the name other decisions refer to the result by, falls back
+    // to the id when the name is missing
+    pub fn result_name(&self) -> String {
+        if self.name.is_empty() {
+            self.id.clone()
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
 impl Diagram {
     pub fn find_decision(&self, decision_id: String) -> Result<Decision, DmnError> {
         match self
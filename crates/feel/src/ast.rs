@@ -1,6 +1,7 @@
 use crate::helpers::{fmt_iter, fmt_vec};
 use crate::scan::TextPosition;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fmt;
 
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
@@ -31,6 +32,19 @@ impl fmt::Display for MapNodeItem {
     }
 }
 
+/// a `name in list` binding of a quantified expression
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct IterBinding {
+    pub var_name: String,
+    pub list_expr: Box<Node>,
+}
+
+impl fmt::Display for IterBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in {}", self.var_name, self.list_expr)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum VarValue {
     Name(String),
@@ -81,6 +95,13 @@ pub enum NodeSyntax {
         right: Box<Node>,
     },
 
+    // value between low and high
+    Between {
+        value: Box<Node>,
+        low: Box<Node>,
+        high: Box<Node>,
+    },
+
     DotOp {
         left: Box<Node>,
         attr: String,
@@ -141,14 +162,12 @@ pub enum NodeSyntax {
     },
 
     SomeExpr {
-        var_name: String,
-        list_expr: Box<Node>,
+        bindings: Vec<IterBinding>,
         filter_expr: Box<Node>,
     },
 
     EveryExpr {
-        var_name: String,
-        list_expr: Box<Node>,
+        bindings: Vec<IterBinding>,
         filter_expr: Box<Node>,
     },
 
@@ -157,6 +176,39 @@ pub enum NodeSyntax {
     UnaryTests(Vec<Box<Node>>),
 }
 
+impl NodeSyntax {
+    /// the name of the syntax kind, e.g. BinOp
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Self::BinOp { .. } => "BinOp",
+            Self::UnaryTest { .. } => "UnaryTest",
+            Self::InOp { .. } => "InOp",
+            Self::LogicOp { .. } => "LogicOp",
+            Self::Between { .. } => "Between",
+            Self::DotOp { .. } => "DotOp",
+            Self::FuncCall { .. } => "FuncCall",
+            Self::FuncDef { .. } => "FuncDef",
+            Self::Var(_) => "Var",
+            Self::Ident(_) => "Ident",
+            Self::Number(_) => "Number",
+            Self::Bool(_) => "Bool",
+            Self::Null => "Null",
+            Self::Str(_) => "Str",
+            Self::Temporal(_) => "Temporal",
+            Self::Neg(_) => "Neg",
+            Self::Array(_) => "Array",
+            Self::Map(_) => "Map",
+            Self::Range { .. } => "Range",
+            Self::IfExpr { .. } => "IfExpr",
+            Self::ForExpr { .. } => "ForExpr",
+            Self::SomeExpr { .. } => "SomeExpr",
+            Self::EveryExpr { .. } => "EveryExpr",
+            Self::ExprList(_) => "ExprList",
+            Self::UnaryTests(_) => "UnaryTests",
+        }
+    }
+}
+
 impl fmt::Display for NodeSyntax {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -164,6 +216,9 @@ impl fmt::Display for NodeSyntax {
             Self::UnaryTest { op, right } => write!(f, "({} {})", op, right),
             Self::InOp { left, right } => write!(f, "(in {} {})", left, right),
             Self::LogicOp { op, left, right } => write!(f, "({} {} {})", op, left, right),
+            Self::Between { value, low, high } => {
+                write!(f, "(between {} {} {})", value, low, high)
+            }
             Self::DotOp { left, attr } => write!(f, "(. {} {})", left, attr),
             Self::FuncCall { func_ref, args } => write!(f, "(call {} ", func_ref)
                 .and_then(|_| fmt_vec(f, args.iter(), "[", "]"))
@@ -206,23 +261,19 @@ impl fmt::Display for NodeSyntax {
                 return_expr,
             } => write!(f, "(for {} in {} {})", var_name, list_expr, return_expr),
             Self::SomeExpr {
-                var_name,
-                list_expr,
+                bindings,
                 filter_expr,
-            } => write!(
-                f,
-                "(some {} in {} satisfies {})",
-                var_name, list_expr, filter_expr
-            ),
+            } => {
+                fmt_vec(f, bindings.iter(), "(some ", "")?;
+                write!(f, " satisfies {})", filter_expr)
+            }
             Self::EveryExpr {
-                var_name,
-                list_expr,
+                bindings,
                 filter_expr,
-            } => write!(
-                f,
-                "(every {} in {} satisfies {})",
-                var_name, list_expr, filter_expr
-            ),
+            } => {
+                fmt_vec(f, bindings.iter(), "(every ", "")?;
+                write!(f, " satisfies {})", filter_expr)
+            }
             Self::ExprList(elements) => fmt_iter(f, elements.iter(), " ", "(expr-list ", ")"),
             Self::UnaryTests(elements) => fmt_iter(f, elements.iter(), " ", "(unary-tests ", ")"),
         }
@@ -252,4 +303,79 @@ impl Node {
     pub fn start_position(&self) -> TextPosition {
         self.start_pos.clone()
     }
+
+    /// collect the names of the variables referred in the node and its
+    /// descendants, including the ones bound inside, e.g. by a for loop
+    pub fn collect_var_names(&self, names: &mut HashSet<String>) {
+        use NodeSyntax::*;
+        match self.syntax.as_ref() {
+            Var(v) => {
+                names.insert(v.value());
+            }
+            BinOp { left, right, .. } | InOp { left, right } | LogicOp { left, right, .. } => {
+                left.collect_var_names(names);
+                right.collect_var_names(names);
+            }
+            UnaryTest { right, .. } => right.collect_var_names(names),
+            Between { value, low, high } => {
+                value.collect_var_names(names);
+                low.collect_var_names(names);
+                high.collect_var_names(names);
+            }
+            DotOp { left, .. } | Neg(left) => left.collect_var_names(names),
+            FuncCall { func_ref, args } => {
+                func_ref.collect_var_names(names);
+                for arg in args.iter() {
+                    arg.arg.collect_var_names(names);
+                }
+            }
+            FuncDef { body, .. } => body.collect_var_names(names),
+            Array(elements) | ExprList(elements) | UnaryTests(elements) => {
+                for element in elements.iter() {
+                    element.collect_var_names(names);
+                }
+            }
+            Map(items) => {
+                for item in items.iter() {
+                    item.name.collect_var_names(names);
+                    item.value.collect_var_names(names);
+                }
+            }
+            Range { start, end, .. } => {
+                start.collect_var_names(names);
+                end.collect_var_names(names);
+            }
+            IfExpr {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                condition.collect_var_names(names);
+                then_branch.collect_var_names(names);
+                else_branch.collect_var_names(names);
+            }
+            ForExpr {
+                list_expr,
+                return_expr,
+                ..
+            } => {
+                list_expr.collect_var_names(names);
+                return_expr.collect_var_names(names);
+            }
+            SomeExpr {
+                bindings,
+                filter_expr,
+            }
+            | EveryExpr {
+                bindings,
+                filter_expr,
+            } => {
+                for binding in bindings.iter() {
+                    binding.list_expr.collect_var_names(names);
+                }
+                filter_expr.collect_var_names(names);
+            }
+            Ident(_) | Number(_) | Bool(_) | Null | Str(_) | Temporal(_) => (),
+        }
+    }
 }
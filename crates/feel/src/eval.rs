@@ -1,6 +1,6 @@
 use std::borrow::Borrow;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error;
 use std::fmt;
 
@@ -11,19 +11,40 @@ use crate::scan::TextPosition;
 
 use self::EvalErrorKind::*;
 
-use super::ast::{FuncCallArg, MapNodeItem, Node, NodeSyntax::*};
+use super::ast::{FuncCallArg, IterBinding, MapNodeItem, Node, NodeSyntax::*};
 use super::helpers::unescape;
 use super::parse::{parse, ParseError, ParseTop};
-use super::prelude::PRELUDE;
+use super::prelude::{add_name_prefixes, Prelude, PRELUDE};
 use super::values::context::Context;
 use super::values::numeric::Numeric;
-use super::values::temporal::parse_temporal;
-use super::values::value::{TypeError, ValueError};
+use super::values::temporal::{duration_component, parse_temporal, temporal_property};
+use super::values::value::{TypeError, ValueError, ValueType};
 
 use super::values::func::{MacroT, NativeFunc};
 use super::values::range::RangeT;
 use super::values::value::Value::{self, *};
 
+// compare by the FEEL semantics, values of different types are
+// neither equal nor unequal and values without an order, e.g. a
+// number and a string or two booleans, cannot be ordered, both give
+// null instead of a misleading false
+fn compare_values(op: &str, left: &Value, right: &Value) -> Value {
+    match op {
+        "=" | "!=" if !left.comparable_with(right) => NullV,
+        "=" => BoolV(left == right),
+        "!=" => BoolV(left != right),
+        _ => match left.partial_cmp(right) {
+            Some(ord) => BoolV(match op {
+                ">" => ord.is_gt(),
+                ">=" => ord.is_ge(),
+                "<" => ord.is_lt(),
+                _ => ord.is_le(),
+            }),
+            None => NullV,
+        },
+    }
+}
+
 // EvalError
 #[derive(Debug, Clone)]
 pub enum EvalErrorKind {
@@ -150,23 +171,187 @@ pub type EvalResult = Result<Value, EvalError>;
 #[derive(Clone)]
 pub struct ScopeFrame {
     vars: HashMap<String, Value>,
+    // the types recorded by set_var_typed(), later typed sets coerce
+    // the values to them
+    types: HashMap<String, ValueType>,
+}
+
+/// the flavor of FEEL semantics the engine follows
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// friendlier defaults, e.g. any value has a truthiness
+    #[default]
+    Loose,
+    /// stick to the DMN 1.2 specification: three-valued logic, boolean
+    /// some/every, only true passes as a condition and null for the
+    /// sum or product of an empty list
+    Dmn12,
+}
+
+/// the events reported to the hook set by Engine::set_event_hook()
+#[derive(Debug, Clone)]
+pub enum EngineEvent {
+    /// a variable set by looking up the stack
+    SetVar(String, Value),
+    /// a variable bound to the top of stack
+    BindVar(String, Value),
+    /// a function called by the name or expression at the call site
+    FuncCall(String),
+    /// an error returned by the outermost evaluation
+    Error(EvalError),
+    /// the syntax kind, the node and its position before evaluating
+    /// the node, reported only when tracing
+    Trace(&'static str, String, TextPosition),
+}
+
+impl fmt::Display for EngineEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::SetVar(name, value) => write!(f, "set {} = {}", name, value),
+            Self::BindVar(name, value) => write!(f, "bind {} = {}", name, value),
+            Self::FuncCall(name) => write!(f, "call {}", name),
+            Self::Error(err) => write!(f, "error {}", err.kind),
+            Self::Trace(kind, node, pos) => write!(f, "trace {} {} at {}", kind, node, pos),
+        }
+    }
+}
+
+/// the scope stack of an engine taken by Engine::snapshot()
+#[derive(Clone)]
+pub struct EngineSnapshot {
+    scopes: Vec<RefCell<ScopeFrame>>,
 }
 
 #[derive(Clone)]
 pub struct Engine {
     scopes: Vec<RefCell<ScopeFrame>>,
+    dialect: Dialect,
+    // report each node to the event hook before evaluating it
+    trace: bool,
+    // max fractional digits of division results, None for the
+    // default 34 digits
+    precision: Option<u32>,
+    // max nesting depth of constructed lists and contexts, None for
+    // no limit
+    max_depth: Option<usize>,
+    // the built-ins, None for the global PRELUDE
+    prelude: Option<Rc<Prelude>>,
+    // receives the engine events, None for no overhead
+    event_hook: Option<Rc<dyn Fn(EngineEvent)>>,
+    // depth of nested eval() calls, counted only when hooked
+    eval_depth: usize,
 }
 
 impl Engine {
     pub fn new() -> Engine {
-        let mut eng = Engine { scopes: Vec::new() };
+        let mut eng = Engine {
+            scopes: Vec::new(),
+            dialect: Dialect::default(),
+            trace: false,
+            precision: None,
+            max_depth: None,
+            prelude: None,
+            event_hook: None,
+            eval_depth: 0,
+        };
         eng.push_frame(); // prelude frame
         eng
     }
 
+    /// an engine resolving the built-ins from the given prelude instead
+    /// of the global one, e.g. a clone of PRELUDE with functions
+    /// removed or added
+    pub fn with_prelude(prelude: Prelude) -> Engine {
+        let mut eng = Engine::new();
+        eng.prelude = Some(Rc::new(prelude));
+        eng
+    }
+
+    fn prelude(&self) -> &Prelude {
+        match &self.prelude {
+            Some(prelude) => prelude,
+            None => &PRELUDE,
+        }
+    }
+
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// whether the value passes as a condition, any value has a
+    /// truthiness in the loose dialect while DMN 1.2 takes only true
+    pub fn is_true(&self, value: &Value) -> bool {
+        match self.dialect {
+            Dialect::Loose => value.bool_value(),
+            Dialect::Dmn12 => *value == BoolV(true),
+        }
+    }
+
+    /// report a Trace event for each node before evaluating it, the
+    /// events go to the hook set by set_event_hook()
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    /// report variable sets and binds, function calls and errors to
+    /// the hook, e.g. to log or audit the evaluation on a host
+    pub fn set_event_hook(&mut self, hook: Box<dyn Fn(EngineEvent)>) {
+        self.event_hook = Some(Rc::from(hook));
+    }
+
+    pub fn clear_event_hook(&mut self) {
+        self.event_hook = None;
+    }
+
+    // the event is only built when there is a hook
+    #[inline(always)]
+    fn emit_event<F: FnOnce() -> EngineEvent>(&self, make_event: F) {
+        if let Some(hook) = &self.event_hook {
+            hook(make_event());
+        }
+    }
+
+    /// cap the fractional digits of division results, e.g. 28 to agree
+    /// with Camunda, the quotient is rounded half even
+    pub fn set_precision(&mut self, precision: u32) {
+        self.precision = Some(precision);
+    }
+
+    /// cap the nesting depth of lists and contexts built by literals
+    /// and `context put`, e.g. 3 allows `[[[1]]]` but not `[[[[1]]]]`
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = Some(max_depth);
+    }
+
+    /// fail when the value is nested deeper than the max depth
+    pub fn check_nesting_depth(&self, value: &Value) -> Result<(), EvalError> {
+        match self.max_depth {
+            Some(max_depth) if value.nesting_depth(max_depth) > max_depth => {
+                Err(EvalError::value_error(
+                    format!("value exceeds the max nesting depth {}", max_depth).as_str(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// the variables of each frame in the scope stack, from the
+    /// outermost frame to the innermost one
+    pub fn dump_scopes(&self) -> Vec<HashMap<String, Value>> {
+        self.scopes
+            .iter()
+            .map(|frame_ref| frame_ref.borrow().vars.clone())
+            .collect()
+    }
+
     pub fn push_frame(&mut self) {
         let frame = ScopeFrame {
             vars: HashMap::new(),
+            types: HashMap::new(),
         };
         self.scopes.push(RefCell::new(frame));
     }
@@ -181,7 +366,7 @@ impl Engine {
                 return Some(v.clone());
             }
         }
-        PRELUDE.resolve(name)
+        self.prelude().resolve(name)
     }
 
     pub fn has_name(&self, name: String) -> bool {
@@ -190,7 +375,27 @@ impl Engine {
                 return true;
             }
         }
-        PRELUDE.has_name(name)
+        self.prelude().has_name(name)
+    }
+
+    /// the prefixes of the variable names made of several words or
+    /// containing ops, e.g. "date", "date and" of "date and time", so
+    /// that the parser can stop collecting name tokens early. the
+    /// prelude names are checked by is_prelude_name_prefix()
+    pub fn compound_name_prefixes(&self) -> HashSet<String> {
+        let mut prefixes: HashSet<String> = HashSet::new();
+        for scope in self.scopes.iter() {
+            for name in scope.borrow().vars.keys() {
+                add_name_prefixes(&mut prefixes, name);
+            }
+        }
+        prefixes
+    }
+
+    /// whether the text is a prefix of a compound built-in name, the
+    /// prefixes are cached on the prelude
+    pub fn is_prelude_name_prefix(&self, text: &str) -> bool {
+        self.prelude().name_prefixes().contains(text)
     }
 
     /// set the value of a variable by look up the stack
@@ -198,6 +403,7 @@ impl Engine {
         if self.scopes.len() == 0 {
             self.push_frame();
         }
+        self.emit_event(|| EngineEvent::SetVar(name.clone(), value.clone()));
 
         for frame_ref in self.scopes.iter().rev() {
             let mut frame = frame_ref.borrow_mut();
@@ -208,11 +414,58 @@ impl Engine {
         }
 
         // if the value not found then set it to the top bar
-        self.bind_var(name, value)
+        self.insert_top_var(name, value)
+    }
+
+    /// set the variable like set_var() and record the type on the
+    /// frame holding it, the value is coerced to the type, e.g. "5"
+    /// becomes 5 for a number, or a TypeError is returned
+    pub fn set_var_typed(
+        &mut self,
+        name: String,
+        value: Value,
+        value_type: ValueType,
+    ) -> EvalResult {
+        let coerced = value.coerce_to(value_type).map_err(|_| {
+            EvalError::type_error(
+                format!(
+                    "{} for `{}`, found {}",
+                    value_type.name(),
+                    name,
+                    value.data_type()
+                )
+                .as_str(),
+            )
+        })?;
+        self.set_var(name.clone(), coerced.clone());
+        for frame_ref in self.scopes.iter().rev() {
+            let mut frame = frame_ref.borrow_mut();
+            if frame.vars.contains_key(&name) {
+                frame.types.insert(name, value_type);
+                break;
+            }
+        }
+        Ok(coerced)
+    }
+
+    /// the type recorded for the variable by set_var_typed()
+    pub fn var_type(&self, name: &str) -> Option<ValueType> {
+        for frame_ref in self.scopes.iter().rev() {
+            let frame = frame_ref.borrow();
+            if frame.vars.contains_key(name) {
+                return frame.types.get(name).copied();
+            }
+        }
+        None
     }
 
     /// bind a variable to the top of stack
     pub fn bind_var(&mut self, name: String, value: Value) {
+        self.emit_event(|| EngineEvent::BindVar(name.clone(), value.clone()));
+        self.insert_top_var(name, value)
+    }
+
+    fn insert_top_var(&mut self, name: String, value: Value) {
         if self.scopes.len() == 0 {
             self.push_frame();
         }
@@ -224,6 +477,52 @@ impl Engine {
             .insert(name, value);
     }
 
+    /// capture the scope stack so that the bindings can be rolled back
+    /// by restore(), values are shared with the engine so lists and
+    /// contexts are not deep copied
+    pub fn snapshot(&self) -> EngineSnapshot {
+        EngineSnapshot {
+            scopes: self.scopes.clone(),
+        }
+    }
+
+    /// bring the scope stack back to the snapshot, dropping variables
+    /// set or bound since then
+    pub fn restore(&mut self, snapshot: EngineSnapshot) {
+        self.scopes = snapshot.scopes;
+    }
+
+    /// drop all user frames and variables, the engine is back to the
+    /// initial state with only the prelude frame
+    pub fn reset(&mut self) {
+        self.reset_frames(false);
+    }
+
+    /// like reset but keep the functions defined by user, e.g. those
+    /// loaded from context, in the prelude frame
+    pub fn reset_keep_functions(&mut self) {
+        self.reset_frames(true);
+    }
+
+    fn reset_frames(&mut self, keep_functions: bool) {
+        let mut funcs: HashMap<String, Value> = HashMap::new();
+        if keep_functions {
+            // inner frames override outer ones
+            for frame_ref in self.scopes.iter() {
+                for (k, v) in frame_ref.borrow().vars.iter() {
+                    if v.is_function() {
+                        funcs.insert(k.clone(), v.clone());
+                    } else {
+                        funcs.remove(k);
+                    }
+                }
+            }
+        }
+        self.scopes.clear();
+        self.push_frame(); // prelude frame
+        self.scopes.last().unwrap().borrow_mut().vars = funcs;
+    }
+
     pub fn as_box(&self) -> Box<Engine> {
         return Box::new(self.clone());
     }
@@ -242,6 +541,10 @@ impl Engine {
         };
     }
 
+    /// bind the entries in a new frame. all entries are bound before
+    /// any function among them is called, and function bodies resolve
+    /// names at call time, so a function can refer to its sibling
+    /// entries, e.g. f() is 5 after loading {a: 5, f: function() a}
     pub fn load_context(&mut self, ctx_entries: Vec<(String, Value)>) {
         self.push_frame();
         //let ctx_entries = context.entries();
@@ -271,7 +574,29 @@ impl Engine {
     }
 
     pub fn eval(&mut self, node: Box<Node>) -> EvalResult {
+        if self.event_hook.is_some() {
+            self.eval_depth += 1;
+            let res = self.eval_node(node);
+            self.eval_depth -= 1;
+            if let (0, Err(err)) = (self.eval_depth, &res) {
+                self.emit_event(|| EngineEvent::Error(err.clone()));
+            }
+            return res;
+        }
+        self.eval_node(node)
+    }
+
+    fn eval_node(&mut self, node: Box<Node>) -> EvalResult {
         let start_pos = node.start_pos;
+        if self.trace {
+            self.emit_event(|| {
+                EngineEvent::Trace(
+                    node.syntax.kind_name(),
+                    node.syntax.to_string(),
+                    start_pos.clone(),
+                )
+            });
+        }
         let res = match *node.syntax {
             Null => Ok(NullV),
             Bool(value) => Ok(BoolV(value)),
@@ -285,6 +610,7 @@ impl Engine {
             UnaryTest { op, right } => self.eval_unary_test(op, right),
             InOp { left, right } => self.eval_in_op(left, right),
             LogicOp { op, left, right } => self.eval_logicop(op, left, right),
+            Between { value, low, high } => self.eval_between(value, low, high),
             DotOp { left, attr } => self.eval_dotop(left, attr),
             Range {
                 start_open,
@@ -298,17 +624,21 @@ impl Engine {
                 arg_names,
                 body,
                 code,
-            } => Ok(FuncV {
-                func_def: Node::new(
-                    FuncDef {
-                        arg_names,
-                        body,
-                        code: code.clone(),
-                    },
-                    start_pos.clone(),
-                ),
-                code,
-            }),
+            } => {
+                let closure = self.capture_scope(&body);
+                Ok(FuncV {
+                    func_def: Node::new(
+                        FuncDef {
+                            arg_names,
+                            body,
+                            code: code.clone(),
+                        },
+                        start_pos.clone(),
+                    ),
+                    code,
+                    closure: Rc::new(closure),
+                })
+            }
             FuncCall { func_ref, args } => self.eval_func_call(func_ref, args),
             IfExpr {
                 condition,
@@ -321,15 +651,13 @@ impl Engine {
                 return_expr,
             } => self.eval_for_expr(var_name, list_expr, return_expr),
             SomeExpr {
-                var_name,
-                list_expr,
+                bindings,
                 filter_expr,
-            } => self.eval_some_expr(var_name, list_expr, filter_expr),
+            } => self.eval_some_expr(bindings, filter_expr),
             EveryExpr {
-                var_name,
-                list_expr,
+                bindings,
                 filter_expr,
-            } => self.eval_every_expr(var_name, list_expr, filter_expr),
+            } => self.eval_every_expr(bindings, filter_expr),
             ExprList(exprs) => self.eval_expr_list(exprs),
             UnaryTests(exprs) => self.eval_unary_tests(exprs),
         };
@@ -346,32 +674,73 @@ impl Engine {
         Ok(StrV(content))
     }
 
+    /// evaluate the node in a fresh child frame, variables bound by the
+    /// node are dropped afterwards. the frame stack is brought back to
+    /// its depth even when the evaluation fails
+    pub fn eval_scoped(&mut self, node: Box<Node>) -> EvalResult {
+        let depth = self.scopes.len();
+        self.push_frame();
+        let res = self.eval(node);
+        self.scopes.truncate(depth);
+        res
+    }
+
     pub fn is_defined(&mut self, value_node: &Box<Node>) -> EvalResult {
-        if let Var(v) = *value_node.syntax.clone() {
-            return match self.resolve(v.value()) {
-                Some(_) => Ok(BoolV(true)),
-                None => Ok(BoolV(false)),
-            };
-        }
         self.push_frame();
-        let r = match self.eval(value_node.clone()) {
-            Ok(_) => Ok(BoolV(true)),
-            Err(EvalError {
-                kind: IndexError,
-                pos: _,
-            })
-            | Err(EvalError {
-                kind: KeyError,
-                pos: _,
-            })
-            | Err(EvalError {
-                kind: VarNotFound(_),
-                pos: _,
-            }) => Ok(BoolV(false)),
-            Err(err) => Err(err),
-        };
+        let r = self.probe_path(value_node.clone());
         self.pop_frame();
-        r
+        match r {
+            Ok(v) => Ok(BoolV(v.is_some())),
+            Err(err) => Err(err),
+        }
+    }
+
+    // resolve the path made of variables, dot ops and index ops step by
+    // step, a missing step gives None instead of an error. the path
+    // itself is evaluated only once so side effects are not repeated,
+    // other expressions are evaluated as they are
+    fn probe_path(&mut self, node: Box<Node>) -> Result<Option<Value>, EvalError> {
+        match *node.syntax {
+            Var(v) => Ok(self.resolve(v.value())),
+            DotOp { left, attr } => match self.probe_path(left)? {
+                Some(left_value) => Ok(attribute_of(&left_value, &attr)),
+                None => Ok(None),
+            },
+            BinOp { op, left, right } if op == "[]" => {
+                let Some(left_value) = self.probe_path(left)? else {
+                    return Ok(None);
+                };
+                let right_value = self.eval(right)?;
+                match self.eval_binop_index(left_value, right_value) {
+                    Ok(v) => Ok(Some(v)),
+                    Err(EvalError {
+                        kind: IndexError,
+                        pos: _,
+                    })
+                    | Err(EvalError {
+                        kind: KeyError,
+                        pos: _,
+                    }) => Ok(None),
+                    Err(err) => Err(err),
+                }
+            }
+            _ => match self.eval(node) {
+                Ok(v) => Ok(Some(v)),
+                Err(EvalError {
+                    kind: IndexError,
+                    pos: _,
+                })
+                | Err(EvalError {
+                    kind: KeyError,
+                    pos: _,
+                })
+                | Err(EvalError {
+                    kind: VarNotFound(_),
+                    pos: _,
+                }) => Ok(None),
+                Err(err) => Err(err),
+            },
+        }
     }
 
     #[inline(always)]
@@ -397,7 +766,9 @@ impl Engine {
             let res = self.eval(elem.clone())?;
             results.push(res);
         }
-        Ok(ArrayV(Rc::new(RefCell::new(results))))
+        let v = ArrayV(Rc::new(RefCell::new(results)));
+        self.check_nesting_depth(&v)?;
+        Ok(v)
     }
 
     #[inline(always)]
@@ -410,7 +781,9 @@ impl Engine {
             let val = self.eval(item.value.clone())?;
             value_map.insert(key, val);
         }
-        Ok(ContextV(Rc::new(RefCell::new(value_map))))
+        let v = ContextV(Rc::new(RefCell::new(value_map)));
+        self.check_nesting_depth(&v)?;
+        Ok(v)
     }
 
     #[inline(always)]
@@ -427,7 +800,7 @@ impl Engine {
         else_branch: Box<Node>,
     ) -> EvalResult {
         let cond_value = self.eval(condition)?;
-        if cond_value.bool_value() {
+        if self.is_true(&cond_value) {
             self.eval(then_branch)
         } else {
             self.eval(else_branch)
@@ -453,6 +826,14 @@ impl Engine {
                 end_node.start_position(),
             ));
         }
+        if !start_value.is_range_endpoint() {
+            return Err(EvalError::new(EvalErrorKind::ValueError(format!(
+                "range endpoint of type {} is not ordered",
+                start_value.data_type()
+            ))));
+        }
+        // a reversed range like [10..1] is kept as it is and treated
+        // as an empty range, following camunda's behavior
         Ok(RangeV(RangeT {
             start_open,
             start: Rc::new(start_value),
@@ -488,64 +869,87 @@ impl Engine {
         }
     }
 
-    fn eval_some_expr(
+    // iterate the cartesian product of the bindings, later lists may
+    // refer to the variables bound before them. the element of a
+    // matching combination is the bound value itself for a single
+    // binding, or the list of bound values otherwise
+    fn eval_quantified(
         &mut self,
-        var_name: String,
-        list_expr: Box<Node>,
-        filter_expr: Box<Node>,
-    ) -> EvalResult {
-        let list_value = self.eval(list_expr)?;
-        match list_value {
-            ArrayV(items) => {
-                let refarr: &RefCell<Vec<Value>> = items.borrow();
-                for item in refarr.borrow().iter() {
-                    self.push_frame();
-                    self.set_var(var_name.clone(), item.clone());
-                    let result = self.eval(filter_expr.clone());
-                    self.pop_frame();
-                    match result {
-                        Ok(v) => {
-                            if v.bool_value() {
-                                return Ok(item.clone());
-                            }
-                        }
-                        Err(err) => return Err(err),
-                    }
+        bindings: &[IterBinding],
+        bound: &mut Vec<Value>,
+        filter_expr: &Node,
+        first_only: bool,
+        results: &mut Vec<Value>,
+        visited: &mut usize,
+    ) -> Result<(), EvalError> {
+        let Some((binding, rest)) = bindings.split_first() else {
+            *visited += 1;
+            let v = self.eval(Box::new(filter_expr.clone()))?;
+            if self.is_true(&v) {
+                if bound.len() == 1 {
+                    results.push(bound[0].clone());
+                } else {
+                    results.push(ArrayV(Rc::new(RefCell::new(bound.clone()))));
                 }
-                Ok(NullV)
             }
-            _ => Err(EvalError::runtime("for loop require a list")),
+            return Ok(());
+        };
+        let list_value = self.eval(binding.list_expr.clone())?;
+        let ArrayV(items) = list_value else {
+            return Err(EvalError::runtime("for loop require a list"));
+        };
+        let items = items.as_ref().borrow().clone();
+        for item in items.into_iter() {
+            self.push_frame();
+            self.set_var(binding.var_name.clone(), item.clone());
+            bound.push(item);
+            let res = self.eval_quantified(rest, bound, filter_expr, first_only, results, visited);
+            bound.pop();
+            self.pop_frame();
+            res?;
+            if first_only && !results.is_empty() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // the first satisfying element or null, DMN 1.2 gives a boolean
+    fn eval_some_expr(&mut self, bindings: Vec<IterBinding>, filter_expr: Box<Node>) -> EvalResult {
+        let mut results: Vec<Value> = vec![];
+        let mut visited = 0;
+        self.eval_quantified(
+            &bindings,
+            &mut vec![],
+            &filter_expr,
+            true,
+            &mut results,
+            &mut visited,
+        )?;
+        match self.dialect {
+            Dialect::Loose => Ok(results.into_iter().next().unwrap_or(NullV)),
+            Dialect::Dmn12 => Ok(BoolV(!results.is_empty())),
         }
     }
 
     fn eval_every_expr(
         &mut self,
-        var_name: String,
-        list_expr: Box<Node>,
+        bindings: Vec<IterBinding>,
         filter_expr: Box<Node>,
     ) -> EvalResult {
-        let list_value = self.eval(list_expr)?;
-        match list_value {
-            ArrayV(items) => {
-                let mut results: Vec<Value> = vec![];
-                let refarr: &RefCell<Vec<Value>> = items.borrow();
-                for item in refarr.borrow().iter() {
-                    self.push_frame();
-                    self.set_var(var_name.clone(), item.clone());
-                    let result = self.eval(filter_expr.clone());
-                    self.pop_frame();
-                    match result {
-                        Ok(v) => {
-                            if v.bool_value() {
-                                results.push(item.clone());
-                            }
-                        }
-                        Err(err) => return Err(err),
-                    }
-                }
-                Ok(ArrayV(Rc::new(RefCell::new(results))))
-            }
-            _ => Err(EvalError::runtime("for loop require a list")),
+        let mut results: Vec<Value> = vec![];
+        let mut visited = 0;
+        self.eval_quantified(
+            &bindings,
+            &mut vec![],
+            &filter_expr,
+            false,
+            &mut results,
+            &mut visited,
+        )?;
+        match self.dialect {
+            Dialect::Loose => Ok(ArrayV(Rc::new(RefCell::new(results)))),
+            Dialect::Dmn12 => Ok(BoolV(results.len() == visited)),
         }
     }
 
@@ -594,6 +998,7 @@ impl Engine {
 
     #[inline(always)]
     fn eval_func_call(&mut self, func_ref: Box<Node>, call_args: Vec<FuncCallArg>) -> EvalResult {
+        self.emit_event(|| EngineEvent::FuncCall(func_ref.syntax.to_string()));
         let fref = self.eval(func_ref)?;
         match fref {
             NativeFuncV {
@@ -602,7 +1007,18 @@ impl Engine {
                 optional_args,
                 var_arg,
             } => self.call_native_func(&func, required_args, optional_args, var_arg, call_args),
-            FuncV { func_def, code: _ } => self.call_func(func_def, call_args),
+            FuncV {
+                func_def,
+                code: _,
+                closure,
+            } => {
+                let mut arg_values: Vec<Value> = Vec::new();
+                for a in call_args {
+                    let v = self.eval(a.arg)?;
+                    arg_values.push(v);
+                }
+                self.call_func(func_def, &closure, arg_values)
+            }
             MacroV {
                 macro_,
                 required_args,
@@ -642,8 +1058,8 @@ impl Engine {
         let mut positional_arg_index = 0;
         // build args
         let mut var_arg_values: Vec<Value> = vec![];
-        let mut use_var_arg = false;
         for call_arg in call_args {
+            let mut use_var_arg = false;
             // resolve argument name
             let arg_name = match call_arg.arg_name.as_str() {
                 "" => {
@@ -664,7 +1080,23 @@ impl Engine {
                     positional_arg_index += 1;
                     implicit_arg_name
                 }
-                a => a,
+                a => {
+                    // the var arg collects positional arguments only
+                    if var_arg.as_deref() == Some(a) {
+                        return Err(EvalError::value_error(
+                            format!("var argument {} cannot be passed by name", a).as_str(),
+                        ));
+                    }
+                    if !required_args.iter().any(|x| x == a)
+                        && !optional_args.iter().any(|x| x == a)
+                        && !func.named_args.iter().any(|x| x == a)
+                    {
+                        return Err(EvalError::value_error(
+                            format!("unknown argument {}", a).as_str(),
+                        ));
+                    }
+                    a
+                }
             };
             if named_args.contains_key(arg_name) {
                 return Err(EvalError::new(EvalErrorKind::ValueError(format!(
@@ -680,8 +1112,17 @@ impl Engine {
             }
         }
 
+        for arg_name in required_args.iter() {
+            if !named_args.contains_key(arg_name) {
+                return Err(EvalError::value_error(
+                    format!("argument {} is required", arg_name).as_str(),
+                ));
+            }
+        }
+
         if var_arg.is_some() {
-            // make var arg as an Array value
+            // make var arg as an Array value, an empty one when no
+            // argument is given so the function body can unwrap it
             let var_arg_name = var_arg.unwrap_or("_".to_string());
             let v = ArrayV(Rc::new(RefCell::new(var_arg_values)));
             named_args.insert(var_arg_name, v);
@@ -711,13 +1152,30 @@ impl Engine {
         (macro_obj.body)(self, args)
     }
 
-    fn call_func(&mut self, func_def: Box<Node>, call_args: Vec<FuncCallArg>) -> EvalResult {
-        let mut arg_values: Vec<Value> = Vec::new();
-        for a in call_args {
-            let v = self.eval(a.arg)?;
-            arg_values.push(v);
+    // the variables referred by the function body and visible from the
+    // current frames except the global frame, which is still reachable
+    // when the function is called
+    fn capture_scope(&self, body: &Node) -> HashMap<String, Value> {
+        let mut names: HashSet<String> = HashSet::new();
+        body.collect_var_names(&mut names);
+        let mut captured: HashMap<String, Value> = HashMap::new();
+        for name in names {
+            for scope in self.scopes.iter().skip(1).rev() {
+                if let Some(value) = scope.borrow().vars.get(&name) {
+                    captured.insert(name, value.clone());
+                    break;
+                }
+            }
         }
+        captured
+    }
 
+    fn call_func(
+        &mut self,
+        func_def: Box<Node>,
+        closure: &HashMap<String, Value>,
+        arg_values: Vec<Value>,
+    ) -> EvalResult {
         if let FuncDef {
             arg_names,
             body,
@@ -729,13 +1187,18 @@ impl Engine {
                     "func call with too few arguments".to_owned(),
                 )));
             }
+            // restore the captured variables under the arguments
+            self.push_frame();
+            for (name, value) in closure.iter() {
+                self.insert_top_var(name.clone(), value.clone());
+            }
             self.push_frame();
-            for (i, arg_name) in arg_names.iter().enumerate() {
-                let value = &arg_values[i];
-                self.set_var(arg_name.clone(), value.clone());
+            for (arg_name, value) in arg_names.into_iter().zip(arg_values) {
+                self.bind_var(arg_name, value);
             }
             let result = self.eval(body);
             self.pop_frame();
+            self.pop_frame();
             result
         } else {
             Err(EvalError::new(Runtime(format!(
@@ -745,14 +1208,70 @@ impl Engine {
         }
     }
 
+    /// call a function value with evaluated positional arguments, used
+    /// by native functions accepting callbacks. macros are not callable
+    /// this way as they take unevaluated nodes
+    pub fn call_function(&mut self, func: &Value, args: Vec<Value>) -> EvalResult {
+        match func {
+            NativeFuncV {
+                func,
+                required_args,
+                optional_args,
+                var_arg,
+            } => {
+                if args.len() < required_args.len()
+                    || (var_arg.is_none() && args.len() > required_args.len() + optional_args.len())
+                {
+                    return Err(EvalError::runtime(
+                        format!("wrong number of arguments calling {}", func.name).as_str(),
+                    ));
+                }
+                let mut named_args: HashMap<String, Value> = HashMap::new();
+                let mut var_arg_values: Vec<Value> = vec![];
+                let mut arg_names = required_args.iter().chain(optional_args.iter());
+                for arg in args {
+                    if let Some(arg_name) = arg_names.next() {
+                        named_args.insert(arg_name.clone(), arg);
+                    } else {
+                        var_arg_values.push(arg);
+                    }
+                }
+                if let Some(var_arg_name) = var_arg {
+                    named_args.insert(
+                        var_arg_name.clone(),
+                        ArrayV(Rc::new(RefCell::new(var_arg_values))),
+                    );
+                }
+                (func.body)(self, named_args)
+            }
+            FuncV {
+                func_def,
+                code: _,
+                closure,
+            } => self.call_func(func_def.clone(), closure, args),
+            _ => Err(EvalError::type_error(
+                format!("function, but {} found", func.data_type()).as_str(),
+            )),
+        }
+    }
+
     // logic ops
+    // both `and` and `or` short-circuit, the right side is not evaluated,
+    // so its side effects don't happen, when the left side decides the
+    // result
     #[inline(always)]
     fn eval_logicop(&mut self, op: String, left: Box<Node>, right: Box<Node>) -> EvalResult {
+        if self.dialect == Dialect::Dmn12 {
+            return self.eval_ternary_logicop(op, left, right);
+        }
         let left_bool_value = self.eval(left)?.bool_value();
         match op.as_str() {
             "and" => {
+                if !left_bool_value {
+                    return Ok(BoolV(false));
+                }
                 let right_value = self.eval(right)?;
-                Ok(BoolV(left_bool_value && right_value.bool_value()))
+                Ok(BoolV(right_value.bool_value()))
             }
             "or" => {
                 if left_bool_value {
@@ -769,6 +1288,51 @@ impl Engine {
         }
     }
 
+    // the three-valued logic of DMN 1.2, a non-boolean operand counts
+    // as null, e.g. `true and null` is null while `false and null` is
+    // false
+    fn eval_ternary_logicop(
+        &mut self,
+        op: String,
+        left: Box<Node>,
+        right: Box<Node>,
+    ) -> EvalResult {
+        let as_bool = |v: Value| match v {
+            BoolV(b) => Some(b),
+            _ => None,
+        };
+        let left_value = as_bool(self.eval(left)?);
+        let res = match op.as_str() {
+            "and" => {
+                if left_value == Some(false) {
+                    return Ok(BoolV(false));
+                }
+                match (left_value, as_bool(self.eval(right)?)) {
+                    (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                }
+            }
+            "or" => {
+                if left_value == Some(true) {
+                    return Ok(BoolV(true));
+                }
+                match (left_value, as_bool(self.eval(right)?)) {
+                    (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                }
+            }
+            _ => {
+                return Err(EvalError::new(Runtime(format!(
+                    "un expected logic op {}",
+                    op
+                ))))
+            }
+        };
+        Ok(res.map_or(NullV, BoolV))
+    }
+
     // binary ops
     #[inline(always)]
     fn eval_binop(&mut self, op: String, left: Box<Node>, right: Box<Node>) -> EvalResult {
@@ -778,14 +1342,29 @@ impl Engine {
             "+" => Ok((left_value + right_value)?),
             "-" => Ok((left_value - right_value)?),
             "*" => Ok((left_value * right_value)?),
-            "/" => Ok((left_value / right_value)?),
+            "/" => match (left_value, right_value, self.precision) {
+                (NumberV(a), NumberV(b), Some(precision)) if b != Numeric::ZERO => {
+                    Ok(NumberV(a.div_with_precision(b, precision)))
+                }
+                (left_value, right_value, _) => Ok((left_value / right_value)?),
+            },
             "%" => Ok((left_value % right_value)?),
-            ">" => Ok(BoolV(left_value > right_value)),
-            ">=" => Ok(BoolV(left_value >= right_value)),
-            "<" => Ok(BoolV(left_value < right_value)),
-            "<=" => Ok(BoolV(left_value <= right_value)),
-            "!=" => Ok(BoolV(left_value != right_value)),
-            "=" => Ok(BoolV(left_value == right_value)),
+            "**" => match (&left_value, &right_value) {
+                (NumberV(a), NumberV(b)) => a.pow(b).map(NumberV).ok_or(EvalError::value_error(
+                    format!("{} ** {} is not a finite number", a, b).as_str(),
+                )),
+                _ => Err(EvalError::type_error(
+                    format!(
+                        "numbers for **, found {} and {}",
+                        left_value.data_type(),
+                        right_value.data_type()
+                    )
+                    .as_str(),
+                )),
+            },
+            ">" | ">=" | "<" | "<=" | "!=" | "=" => {
+                Ok(compare_values(&op, &left_value, &right_value))
+            }
             "[]" => self.eval_binop_index(left_value, right_value),
             //"in" => self.eval_binop_in(left_value, right_value),
             _ => return Err(EvalError::new(Runtime(format!("unknown op {}", op)))),
@@ -797,12 +1376,9 @@ impl Engine {
         let left_value = self.eval_var(VarValue::Name("?".to_owned()))?;
         let right_value = self.eval(right)?;
         match op.as_str() {
-            ">" => Ok(BoolV(left_value > right_value)),
-            ">=" => Ok(BoolV(left_value >= right_value)),
-            "<" => Ok(BoolV(left_value < right_value)),
-            "<=" => Ok(BoolV(left_value <= right_value)),
-            "!=" => Ok(BoolV(left_value != right_value)),
-            "=" => Ok(BoolV(left_value == right_value)),
+            ">" | ">=" | "<" | "<=" | "!=" | "=" => {
+                Ok(compare_values(&op, &left_value, &right_value))
+            }
             //"in" => self.eval_binop_in(left_value, right_value),
             _ => {
                 return Err(EvalError::new(Runtime(format!(
@@ -836,7 +1412,7 @@ impl Engine {
                     {
                         return Err(EvalError::new(IndexError));
                     }
-                    let idx0 = idx.to_usize().unwrap();
+                    let idx0 = idx.to_usize().ok_or(EvalError::new(IndexError))?;
 
                     let v = arr.get(idx0 - 1).ok_or(EvalError::new(IndexError))?;
                     Ok(v.clone())
@@ -850,6 +1426,26 @@ impl Engine {
         }
     }
 
+    #[inline(always)]
+    fn eval_between(&mut self, value: Box<Node>, low: Box<Node>, high: Box<Node>) -> EvalResult {
+        let value = self.eval(value)?;
+        let low_value = self.eval(low)?;
+        let high_value = self.eval(high)?;
+        for endpoint in [&low_value, &high_value] {
+            if endpoint.data_type() != value.data_type() {
+                return Err(EvalError::type_error(
+                    format!(
+                        "cannot compare {} between {}",
+                        value.data_type(),
+                        endpoint.data_type()
+                    )
+                    .as_str(),
+                ));
+            }
+        }
+        Ok(BoolV(value >= low_value && value <= high_value))
+    }
+
     #[inline(always)]
     fn eval_in_op(&mut self, left: Box<Node>, right: Box<Node>) -> EvalResult {
         let left_value = self.eval(left)?;
@@ -863,10 +1459,7 @@ impl Engine {
             }
             _ => (),
         }
-        self.push_frame();
-        let right_res = self.eval(right);
-        self.pop_frame();
-        let right_value = right_res?;
+        let right_value = self.eval_scoped(right)?;
         match right_value {
             RangeV(rng) => {
                 let contains = rng.contains(&left_value);
@@ -891,22 +1484,48 @@ impl Engine {
     #[inline(always)]
     fn eval_dotop(&mut self, left: Box<Node>, attr: String) -> EvalResult {
         let left_value = self.eval(left)?;
+        if let Some(v) = attribute_of(&left_value, &attr) {
+            return Ok(v);
+        }
         match left_value {
-            ContextV(a) => {
-                let refctx: &RefCell<Context> = a.borrow();
-                //let m = a.borrow();
-                let v = refctx.borrow().get(attr).ok_or(EvalError::new(KeyError))?;
-                Ok(v)
-            }
-            _ => Err(EvalError::runtime("map is not indexable")),
+            ContextV(_) => Err(EvalError::new(KeyError)),
+            DurationV { .. } => Err(EvalError::value_error(
+                format!("duration has no component `{}`", attr).as_str(),
+            )),
+            DateV(_) | TimeV(_) | DateTimeV(_) => Err(EvalError::value_error(
+                format!("{} has no property `{}`", left_value.data_type(), attr).as_str(),
+            )),
+            _ => Err(EvalError::new(Runtime(format!(
+                "value {} has no attribute `{}`",
+                left_value.data_type(),
+                attr
+            )))),
+        }
+    }
+}
+
+// the attribute of a value by the dot op, the entry of a context, the
+// component of a duration or the property of a date or time. None
+// when there is no such attribute
+fn attribute_of(value: &Value, attr: &str) -> Option<Value> {
+    match value {
+        ContextV(a) => {
+            let refctx: &RefCell<Context> = a.borrow();
+            let v = refctx.borrow().get(attr.to_owned());
+            v
+        }
+        DurationV { duration, negative } => {
+            duration_component(duration, *negative, attr).map(|n| NumberV(Numeric::from_i64(n)))
         }
+        DateV(_) | TimeV(_) | DateTimeV(_) => temporal_property(value, attr),
+        _ => None,
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{parse::parse, values::numeric::Numeric};
-    use core::assert_matches::assert_matches;
+    use crate::{parse::parse, values::numeric::Numeric, values::value::ValueType};
+    use core::assert_matches;
 
     #[test]
     fn test_number_parse() {
@@ -924,6 +1543,16 @@ mod test {
             (None, "10 / 3", "3.3333333333333333333333333333333333"), // precision is up to 34
             (None, "4 * 9 + 1", "37"),
             (None, "8 % 5", "3"),
+            (None, "10 mod 3", "1"),
+            (None, "10 mod 3 = 10 % 3", "true"),
+            (None, "2 * 7 mod 4", "2"),
+            (None, "mode([1, 2, 2])", "[2]"),
+            (None, "modulo(10, 3)", "1"),
+            (Some("{x: {mod: 5}}"), "x.mod mod 3", "2"),
+            (Some("{mod: 3}"), "mod + 1", "4"),
+            (Some("{mod: 3}"), "mod mod 2", "1"),
+            (None, "1_000_000 + 1", "1000001"),
+            (None, "1_000.5", "1000.5"),
             (None, "8 / 5", "1.6"),
             (None, "true and false", "false"),
             (None, "false or 2", "true"),
@@ -941,11 +1570,58 @@ mod test {
             (
                 None,
                 r#" @"2023-06-01T10:33:20+01:00" - @"2022-04-01T10:33:20+01:00" "#,
-                r#"duration("P426DT0.2446661632S")"#,
+                r#"duration("P426D")"#,
             ),
             (None, r#"@"2023-09-17" < @"2023-10-02""#, "true"),
+            // durations are ordered by magnitude
+            (None, r#"@"PT1H" < @"PT90M""#, "true"),
+            (None, r#"@"P1D" > @"PT23H""#, "true"),
+            (None, r#"@"P1Y" > @"P11M""#, "true"),
+            (None, r#"@"-PT1H" < @"PT1M""#, "true"),
+            (None, r#"@"PT60M" >= @"PT1H""#, "true"),
+            (None, r#"@"P1M" < @"P30D""#, "null"), // different families
+            (None, r#"@"P1M" > @"P30D""#, "null"),
+            (None, r#"@"P1M" > @"PT0S""#, "null"),
+            (None, r#"@"P1M" > @"P1M1D""#, "null"),
+            // and equal when they have the same magnitude
+            (None, r#"@"PT1H" = @"PT60M""#, "true"),
+            (None, r#"@"P1Y" = @"P12M""#, "true"),
+            (None, r#"@"PT1H" != @"PT61M""#, "true"),
+            (
+                None,
+                r#"distinct values([@"PT1H", @"PT60M", @"P1D", @"PT24H"])"#,
+                r#"[duration("PT1H"), duration("P1D")]"#,
+            ),
+            (None, r#"1 = "1""#, "null"), // different types
+            (None, r#"1 != "1""#, "null"),
+            (None, r#"1 < "a""#, "null"),
+            (None, "true < false", "null"),
+            (None, "1 = null", "false"),
+            (None, "null = null", "true"),
+            (None, "null < 1", "null"),
+            (None, r#""a" < "b""#, "true"),
+            (
+                None,
+                r#"sort([@"PT2H", @"PT30M", @"P1D"])"#,
+                r#"[duration("PT30M"), duration("PT2H"), duration("P1D")]"#,
+            ),
+            (None, r#"max(@"PT2H", @"PT30M")"#, r#"duration("PT2H")"#),
+            (None, r#"min(@"P2Y", @"P3M")"#, r#"duration("P3M")"#),
             (None, r#""abc" + "de\\nf""#, r#""abcde\nf""#),
             (None, "2 < 3 - 1", "false"),
+            // structural equality of lists and contexts
+            (None, "[1,[2,3]] = [1,[2,3]]", "true"),
+            (None, "[1,[2,3]] = [1,[3,2]]", "false"),
+            (None, "{a:1} = {a:1}", "true"),
+            (None, "{a:1, b:{c:[1, 2.0]}} = {b:{c:[1, 2]}, a:1}", "true"),
+            (None, "{a:1, b:{c:[1, 2]}} = {a:1, b:{c:[1]}}", "false"),
+            // numbers equal regardless of scale
+            (None, "1.0 = 1", "true"),
+            (None, "1.50 = 1.5", "true"),
+            (None, "0 = -0", "true"),
+            (None, "0.00 = -0", "true"),
+            (None, "1.0 != 1", "false"),
+            (None, "1.5 in [1, 1.50]", "true"),
             (None, r#""abc" <= "abd""#, "true"),
             (None, "[6, 1, 2, -3][4]", "-3"),
             (None, "[2, 8,false,true]", "[2, 8, false, true]"),
@@ -956,11 +1632,45 @@ mod test {
             (None, "8 in [5..8)", "false"),
             (None, "8 in [5..8]", "true"),
             (None, r#" "c" in ["a".."z"]"#, "true"),
+            (None, r#"5 in ["a".."z"]"#, "false"),
+            (None, r#""c" in [1..10]"#, "false"),
+            (None, r#""c" in (1..10)"#, "false"),
+            (None, r#"@"2023-01-01" in [1..10]"#, "false"),
             (None, r#" "f" in ["a".."f")"#, "false"),
             (None, "7 in [2, 7, 8]", "true"),
             (None, "7 in [3, 99, -1]", "false"),
-            // if expr
-            (None, "if 2 > 3 then 6 else 8", "8"),
+            (None, "5 in [10..1]", "false"), // reversed range is empty
+            (None, "10 in [10..1]", "false"),
+            // cartesian product
+            (
+                None,
+                "cartesian([1,2], [3,4])",
+                "[[1, 3], [1, 4], [2, 3], [2, 4]]",
+            ),
+            (
+                None,
+                r#"cartesian([1,2], ["a"], [true, false])"#,
+                r#"[[1, "a", true], [1, "a", false], [2, "a", true], [2, "a", false]]"#,
+            ),
+            (None, "cartesian([1,2])", "[[1], [2]]"),
+            (None, "cartesian([1,2], [])", "[]"),
+            (None, "cartesian()", "[]"),
+            // between
+            (None, "5 between 1 and 10", "true"),
+            (None, "10 between 1 and 10", "true"),
+            (None, "11 between 1 and 10", "false"),
+            (None, r#""m" between "a" and "z""#, "true"),
+            (
+                Some(r#"{a: 3, b: 5}"#),
+                "a between 1 and b and b between a and 4",
+                "false",
+            ),
+            // unicode names
+            (Some(r#"{café: 3, 名前: "x"}"#), "café * 2", "6"),
+            (Some(r#"{café: 3, 名前: "x"}"#), "名前", r#""x""#),
+            (Some(r#"{"straße länge": 4}"#), "straße länge + 1", "5"),
+            // if expr
+            (None, "if 2 > 3 then 6 else 8", "8"),
             (None, "for a in [2, 3, 4] return a * 2", "[4, 6, 8]"), // simple for loop
             (
                 None,
@@ -974,6 +1684,26 @@ mod test {
             ),
             (None, "some a in [2, 8, 3, 6] satisfies a > 4", "8"),
             (None, "every a in [2, 8, 3, 6] satisfies a > 4", "[8, 6]"),
+            (
+                None,
+                "some x in [1, 2], y in [3, 4] satisfies x + y = 5",
+                "[1, 4]",
+            ),
+            (
+                None,
+                "some x in [1, 2], y in [3, 4] satisfies x + y = 9",
+                "null",
+            ),
+            (
+                None,
+                "every x in [1, 2], y in [1, 2] satisfies x <= y",
+                "[[1, 1], [1, 2], [2, 2]]",
+            ),
+            (
+                None,
+                "some x in [1, 2], y in [x * 2] satisfies y = 4",
+                "[2, 4]",
+            ),
             //("2 * 8; true; null; 9 / 3", "3"),
             (None, "2 in (>=5, <3)", "true"),
             (Some("{a: 5}"), r#"a + 10.3"#, "15.3"), // expression list
@@ -987,6 +1717,35 @@ mod test {
             (None, r#"is defined([1, 2][1])"#, "true"),
             (None, r#"is defined([1, 2][-1])"#, "false"),
             (None, r#"is defined([1, 2][6])"#, "false"),
+            (None, r#"{"in": 1}["in"]"#, "1"),
+            (None, r#"{"in": 1}.`in`"#, "1"),
+            (None, r#"{"for": 2}.for"#, "2"),
+            (None, "{`return`: 3}", r#"{"return":3}"#),
+            (Some(r#"{"in": {"function": 4}}"#), "`in`.function", "4"),
+            (Some(r#"{x: {in: 5}}"#), "x.in in [5]", "true"),
+            (Some("{a: {b: {c: 1}}}"), "is defined(a.b.c)", "true"),
+            (Some("{a: {b: {c: 1}}}"), "is defined(a.b.d)", "false"),
+            (
+                Some("{a: {b: {c: 1}}}"),
+                "is defined(a.missing.deeper)",
+                "false",
+            ),
+            (
+                Some("{a: {b: {c: 1}}}"),
+                "is defined(a.b.c.deeper)",
+                "false",
+            ),
+            (Some("{a: {b: [{c: 1}]}}"), "is defined(a.b[1].c)", "true"),
+            (Some("{a: {b: [{c: 1}]}}"), "is defined(a.b[2].c)", "false"),
+            (None, r#"is defined(date("2020-01-02").year)"#, "true"),
+            (None, r#"is defined(date("2020-01-02").foo)"#, "false"),
+            (None, r#"is defined(@"P1DT2H".hours)"#, "true"),
+            (None, r#"is defined(@"P1DT2H".foo)"#, "false"),
+            (
+                Some("{a: {b: [{c: 1}]}}"),
+                "is defined(missing.b[1].c)",
+                "false",
+            ),
             // test prelude functions
             (None, "not(2>1)", "false"),
             (None, r#"number("3000.88800")"#, "3000.88800"),
@@ -1003,6 +1762,8 @@ mod test {
             // number functions
             (None, "decimal(1/3, 2)", "0.33"),
             (None, "decimal(1.5, 0)", "2"),
+            (None, "decimal(2, 2)", "2.00"),
+            (None, "decimal(2, 2) = 2", "true"),
             (None, "decimal(1.5)", "1.5"),
             (None, r#"decimal("1.56", 9)"#, "1.560000000"),
             (None, "floor(1.5)", "1"),
@@ -1012,6 +1773,11 @@ mod test {
             (None, "ceiling(-1.5)", "-1"),
             (None, "ceiling(-1.56, 1)", "-1.5"),
             (None, "decimal(log(10), 12)", "2.302585092994"),
+            (None, "decimal(log(8, 2), 6)", "3.000000"),
+            (None, "decimal(log10(1000), 6)", "3.000000"),
+            (None, "decimal(log2(1024), 6)", "10.000000"),
+            (None, "decimal(exp(1), 6)", "2.718282"),
+            (None, "exp(0)", "1"),
             (None, "odd(5)", "true"),
             (None, "odd(2)", "false"),
             (None, "even(5)", "false"),
@@ -1021,13 +1787,299 @@ mod test {
             (None, r#"list contains([2, 8, "hello"], "world")"#, "false"),
             (None, "count(1, 2, 4, 9, -3)", "5"),
             (None, "count()", "0"),
+            (None, "concatenate()", "[]"),
+            (None, "union()", "[]"),
+            (None, "count([])", "0"),
+            (None, "count([1, 2, 3])", "3"),
+            (None, "count([[1, 2]])", "1"),
+            (None, "{}", "{}"),
+            (None, "[]", "[]"),
+            (None, "get entries({})", "[]"),
+            (None, "get keys({})", "[]"),
+            (None, "{} = {}", "true"),
+            (None, "[] = []", "true"),
+            (None, "{} = {a: 1}", "false"),
+            (None, "[] = [1]", "false"),
+            (None, "{} != {}", "false"),
+            (None, "sum([])", "0"),
+            (None, "concatenate([], [])", "[]"),
             (None, "min(31, -1, 9, 8, -1, -99)", "-99"),
             (None, "min(31, -1, 9, false, -1, -99)", "-99"),
             (None, "max(31, -1, 9, 8, -1, -99)", "31"),
             (None, "sum(31, -1, 9, false, -1, -99)", "-61"),
+            (None, "strict sum(31, -1, 9, 8, -1, -99)", "-53"),
+            (None, "strict min(31, -1, 9, 8, -1, -99)", "-99"),
+            (None, "strict max(31, -1, 9, 8, -1, -99)", "31"),
+            (None, r#"strict max("a", "c", "b")"#, r#""c""#),
+            (None, "strict mean(1, 2, 3, 6)", "3"),
+            (None, "strict stddev(2, 4, 4, 4, 5, 5, 7, 9)", "2"),
+            (None, "stddev([2, 4, 4, 4, 5, 5, 7, 9], sample: false)", "2"),
+            (
+                None,
+                "stddev([2, 4, 4, 4, 5, 5, 7, 9], sample: true)",
+                "2.1380899352993950774764278470380281",
+            ),
+            (None, "stddev(1, 3, sample: true) > stddev(1, 3)", "true"),
+            (None, "strict stddev([1, 3, 5], sample: true)", "2"),
+            (None, "stddev([])", "null"),
+            (None, "mean(1, 2, false, 6)", "3"),
+            (None, "sum([1, 2, 3])", "6"),
+            (None, "max([1, 5, 3])", "5"),
+            (None, "min(false, 3, 2)", "2"),
+            (
+                None,
+                r#"min([date("2023-01-01"), date("2022-01-01"), date("2022-06-30")])"#,
+                r#"date("2022-01-01")"#,
+            ),
+            (
+                None,
+                r#"max([date("2023-01-01"), date("2022-01-01"), date("2022-06-30")])"#,
+                r#"date("2023-01-01")"#,
+            ),
+            (
+                None,
+                r#"min([time("10:00:00"), time("09:30:00"), time("11:00:00")])"#,
+                r#"time("09:30:00.0+00:00")"#,
+            ),
+            (
+                None,
+                r#"max(time("10:00:00+01:00"), time("09:30:00Z"))"#,
+                r#"time("09:30:00.0+00:00")"#,
+            ),
+            // and equal at UTC as well
+            (
+                None,
+                r#"time("10:00:00+01:00") = time("09:00:00Z")"#,
+                "true",
+            ),
+            (
+                None,
+                r#"time("10:00:00+01:00") != time("10:00:00Z")"#,
+                "true",
+            ),
+            (
+                None,
+                r#"count(distinct values([time("10:00:00+01:00"), time("09:00:00Z")]))"#,
+                "1",
+            ),
+            (
+                None,
+                r#"sort([time("10:00:00"), time("09:00:00")])"#,
+                r#"[time("09:00:00.0+00:00"), time("10:00:00.0+00:00")]"#,
+            ),
+            (
+                None,
+                r#"max([@"2020-01-01T10:00:00Z", @"2021-01-01T10:00:00Z"])"#,
+                r#"date and time("2021-01-01T10:00:00+00:00")"#,
+            ),
+            (
+                None,
+                r#"abs(@"2020-01-01T00:00:00Z" - @"2020-01-01T01:00:00Z")"#,
+                r#"duration("PT1H")"#,
+            ),
+            (None, "strict min([4, 2, 3])", "2"),
             (None, "sort([3, -1, 2])", "[-1, 2, 3]"),
+            (None, "sort descending([3, -1, 2, 3])", "[3, 3, 2, -1]"),
+            (
+                None,
+                r#"sort descending(["b", "a", "c", "b"])"#,
+                r#"["c", "b", "b", "a"]"#,
+            ),
+            (None, "sort descending([])", "[]"),
+            // equal instants in different offsets keep their order
+            (
+                None,
+                r#"sort descending([@"2020-01-01T10:00:00+01:00", @"2020-01-02T00:00:00Z", @"2020-01-01T09:00:00Z"])"#,
+                r#"[date and time("2020-01-02T00:00:00+00:00"), date and time("2020-01-01T10:00:00+01:00"), date and time("2020-01-01T09:00:00+00:00")]"#,
+            ),
+            (
+                None,
+                "sort descending([2, 10, 1]) = reverse(sort([2, 10, 1]))",
+                "true",
+            ),
+            (None, "mode([6, 3, 9, 6, 6])", "[6]"),
+            (None, "mode([6, 1, 9, 6, 1])", "[1, 6]"),
+            (None, "mode([])", "[]"),
+            (None, "percentile([1,2,3,4], 0.5)", "2.5"),
+            (None, "percentile([4,1,3,2], 1)", "4"),
+            (None, "percentile([1,2,3,4], 0)", "1"),
             (None, "sublist([1,2,3], 2)", "[2, 3]"),
             (None, "sublist([1,2,3], 1, 2)", "[1, 2]"),
+            (None, r#"index of string("abcabc", "bc")"#, "2"),
+            (None, r#"last index of string("abcabc", "bc")"#, "5"),
+            (None, r#"index of string("abcabc", "x")"#, "null"),
+            (None, r#"last index of string("abcabc", "x")"#, "null"),
+            (None, r#"index of string("日本語の本", "本")"#, "2"),
+            (None, r#"last index of string("日本語の本", "本")"#, "5"),
+            // keys are sorted rather than in the written order
+            (None, "get keys({b: 2, a: 1})", r#"["a", "b"]"#),
+            (None, "get keys({z: 1, y: 2, x: 3})", r#"["x", "y", "z"]"#),
+            (None, "get values({b: 2, a: 1})", "[1, 2]"),
+            (None, "get keys({})", "[]"),
+            (
+                None,
+                "get keys({b: 2, a: 1}) = for e in get entries({b: 2, a: 1}) return e.key",
+                "true",
+            ),
+            (
+                None,
+                "get values({b: 2, a: 1}) = for e in get entries({b: 2, a: 1}) return e.value",
+                "true",
+            ),
+            (None, r#"coerce("5", "number")"#, "5"),
+            (None, r#"coerce(5, "string")"#, r#""5""#),
+            (None, r#"coerce("true", "boolean")"#, "true"),
+            (
+                None,
+                r#"coerce("2023-06-01", "date")"#,
+                r#"date("2023-06-01")"#,
+            ),
+            (None, r#"coerce(@"P1D", "string")"#, r#""P1D""#),
+            (None, r#"coerce(3, "list")"#, "[3]"),
+            (None, r#"coerce([3], "list")"#, "[3]"),
+            (None, r#"coerce("abc", "number")"#, "null"),
+            (None, r#"coerce([1, 2], "number")"#, "null"),
+            (
+                Some("{orders: [{total: 10, qty: 2}, {total: 5, qty: 1}, {total: 30, qty: 3}]}"),
+                "sum over(orders, total)",
+                "45",
+            ),
+            (
+                Some("{orders: [{total: 10, qty: 2}, {total: 5, qty: 1}, {total: 30, qty: 3}]}"),
+                "mean over(orders, total)",
+                "15",
+            ),
+            (
+                Some("{orders: [{total: 10, qty: 2}, {total: 5, qty: 1}, {total: 30, qty: 3}]}"),
+                "sum over(orders, total * qty)",
+                "115",
+            ),
+            (
+                Some("{orders: [{total: 10, qty: 2}, {total: 5, qty: 1}, {total: 30, qty: 3}]}"),
+                "max over(orders, qty) + min over(orders, qty)",
+                "4",
+            ),
+            (None, "sum over([1, 2, 3], item * 2)", "12"),
+            (None, "mean over([], item)", "null"),
+            (None, "starts with([1, 2, 3], [1, 2])", "true"),
+            (None, "starts with([1, 2, 3], [2, 3])", "false"),
+            (None, "starts with([1], [1, 2])", "false"),
+            (None, "ends with([1, 2, 3], [2, 3])", "true"),
+            (None, "ends with([1, 2, 3], [1, 2])", "false"),
+            (None, "ends with([1, 2, 3], [])", "true"),
+            (None, "contains sublist([1, 2, 3, 4], [2, 3])", "true"),
+            (None, "contains sublist([1, 2, 3, 4], [2, 4])", "false"),
+            (None, "contains sublist([1, 2], [1, 2, 3])", "false"),
+            (None, "contains sublist([1, 2], [])", "true"),
+            (None, r#"starts with("abc", "ab")"#, "true"),
+            (None, "distinct values([1, 2, 1, 3, 2])", "[1, 2, 3]"),
+            (None, "distinct values last([1, 2, 1, 3, 2])", "[1, 3, 2]"),
+            (None, "distinct values last([])", "[]"),
+            (None, "percent(15)", "0.15"),
+            (None, "percent(12.5)", "0.125"),
+            (None, "percent of(15, 200)", "30"),
+            (None, "percent of(2.5, 80)", "2"),
+            (None, "percent of(12.5, 10)", "1.25"),
+            (
+                None,
+                "run length encode([1, 1, 2, 3, 3, 3])",
+                r#"[{"count":2, "value":1}, {"count":1, "value":2}, {"count":3, "value":3}]"#,
+            ),
+            (None, "run length encode([])", "[]"),
+            (
+                None,
+                r#"run length decode([{value: "a", count: 2}, {value: "b", count: 1}])"#,
+                r#"["a", "a", "b"]"#,
+            ),
+            (
+                None,
+                "run length decode(run length encode([1, 1, 2, 1, 3, 3]))",
+                "[1, 1, 2, 1, 3, 3]",
+            ),
+            (
+                None,
+                r#"run length decode(run length encode(["x", "x", null, null]))"#,
+                r#"["x", "x", null, null]"#,
+            ),
+            (None, r#"concat("a", "b", "c")"#, r#""abc""#),
+            (None, r#"concat("名", "前")"#, r#""名前""#),
+            (None, "concat()", r#""""#),
+            (None, r#"invert({a: "x", b: "y"})"#, r#"{"x":"a", "y":"b"}"#),
+            (
+                None,
+                r#"invert({a: "x", b: "x", c: "y"})"#,
+                r#"{"x":"b", "y":"c"}"#,
+            ),
+            (None, "invert({a: 1, b: true})", r#"{"1":"a", "true":"b"}"#),
+            (None, r#"invert({a: "x", b: "y"}).x"#, r#""a""#),
+            (
+                None,
+                r#"zip with index(["a", "b"])"#,
+                r#"[{"index":1, "value":"a"}, {"index":2, "value":"b"}]"#,
+            ),
+            (None, "zip with index([])", "[]"),
+            (
+                None,
+                r#"for x in zip with index(["a", "b", "c"]) return string(x.index) + x.value"#,
+                r#"["1a", "2b", "3c"]"#,
+            ),
+            (None, "flatten([1, [2, [3, [4]]]])", "[1, 2, 3, 4]"),
+            (None, "flatten([1, [2, [3, [4]]]], 1)", "[1, 2, [3, [4]]]"),
+            (None, "flatten([1, [2, [3, [4]]]], 2)", "[1, 2, 3, [4]]"),
+            (None, "flatten([1, [2, [3, [4]]]], 0)", "[1, 2, 3, 4]"),
+            (None, "flatten([[], [[]], 5])", "[5]"),
+            (None, "truncate(-1.5)", "-1"),
+            (None, "floor(-1.5)", "-2"),
+            (None, "truncate(1.5)", "1"),
+            (None, "truncate(7)", "7"),
+            (None, "truncate(-0.5)", "0"),
+            (None, "is integer(2.0)", "true"),
+            (None, "is integer(2.5)", "false"),
+            (None, "is integer(-3)", "true"),
+            (None, r#"is integer("2")"#, "false"),
+            (None, "first([3, 1, 2])", "3"),
+            (None, "last([3, 1, 2])", "2"),
+            (None, "first([])", "null"),
+            (None, "last([])", "null"),
+            (None, "find last([1, 4, 3, 6, 5], function(x) x > 3)", "5"),
+            (None, "find last([1, 2, 3], function(x) x > 3)", "null"),
+            (None, "find last([], function(x) true)", "null"),
+            (None, "(function(x) function(y) x + y)(3)(4)", "7"),
+            (
+                None,
+                "(for i in [1,2,3] return function(x) x + i)[2](10)",
+                "12",
+            ),
+            (
+                None,
+                "for f in (for i in [1,2,3] return function() i * 10) return f()",
+                "[10, 20, 30]",
+            ),
+            (None, "(function(x) function(x) x * 2)(3)(5)", "10"),
+            (
+                None,
+                "for x in [1] return [(function(x) x)(5), x]",
+                "[[5, 1]]",
+            ),
+            (
+                None,
+                "count by([1,2,2,3,3,3], function(x) x)",
+                r#"{"1":1, "2":2, "3":3}"#,
+            ),
+            (
+                None,
+                r#"count by(["apple", "avocado", "banana"], function(s) substring(s, 1, 1))"#,
+                r#"{"a":2, "b":1}"#,
+            ),
+            (None, "count by([], function(x) x)", "{}"),
+            (
+                None,
+                "count by([-1, 2, -3], abs)",
+                r#"{"1":1, "2":1, "3":1}"#,
+            ),
+            (None, "sublist([1,2,3,4], -2)", "[3, 4]"),
+            (None, "sublist([1,2,3,4], -2, 1)", "[3]"),
+            (None, "sublist([1,2,3,4], -4)", "[1, 2, 3, 4]"),
             (None, "append([1], 2, 3)", "[1, 2, 3]"),
             (None, "append([1, 2, 3])", "[1, 2, 3]"),
             (None, "concatenate([1,2],[3])", "[1, 2, 3]"),
@@ -1036,6 +2088,162 @@ mod test {
             (None, "remove([1,2,3], 2)", "[1, 3]"),
             (None, "reverse([1,2,3])", "[3, 2, 1]"),
             (None, "index of([1,2,3,2], 2)", "[2, 4]"),
+            (None, "first index of([1,2,3,2], 2)", "2"),
+            (None, "2 ** 3 ** 2", "512"),
+            (None, ".5", "0.5"),
+            (None, "1 + .25", "1.25"),
+            (None, "5.5", "5.5"),
+            (None, "[.5..1.5]", "[0.5..1.5]"),
+            (None, r#"deep get({a: {b: {c: 3}}}, "a.b.c")"#, "3"),
+            (None, r#"deep get({a: {b: {c: 3}}}, "a.b")"#, r#"{"c":3}"#),
+            (None, r#"deep get({a: {b: 1}}, "a.x.y")"#, "null"),
+            (None, r#"deep get({"a.b": 5}, "a\\.b")"#, "5"),
+            (
+                None,
+                r#"deep put({a: {b: 1}}, "a.c.d", 2)"#,
+                r#"{"a":{"b":1, "c":{"d":2}}}"#,
+            ),
+            (
+                None,
+                r#"deep put({}, "x\\.y.z", true)"#,
+                r#"{"x.y":{"z":true}}"#,
+            ),
+            (None, r#"deep get(deep put({}, "p.q", 7), "p.q")"#, "7"),
+            (None, r#"replace literal("a.b.c", ".", "-")"#, r#""a-b-c""#),
+            (
+                None,
+                r#"replace literal("f(x) + f(y)", "f(", "g(")"#,
+                r#""g(x) + g(y)""#,
+            ),
+            (None, r#"replace literal("abc", "x", "-")"#, r#""abc""#),
+            (None, r#"replace first("a.b.c", ".", "-")"#, r#""a-b.c""#),
+            (
+                None,
+                r#"replace first("名前名前", "名", "")"#,
+                r#""前名前""#,
+            ),
+            (
+                None,
+                r#"sum([duration("P1D"), duration("P2D")])"#,
+                r#"duration("P3D")"#,
+            ),
+            (
+                None,
+                r#"mean([duration("P1D"), duration("P2D")])"#,
+                r#"duration("P1DT12H")"#,
+            ),
+            (
+                None,
+                r#"sum(duration("PT50M"), duration("PT20M"), 5)"#,
+                r#"duration("PT1H10M")"#,
+            ),
+            (
+                None,
+                r#"sum([duration("P1Y"), duration("P6M"), duration("P8M")])"#,
+                r#"duration("P2Y2M")"#,
+            ),
+            (
+                None,
+                r#"sum(duration("P2D"), date and time("2020-01-01T00:00:00") - date and time("2020-01-04T00:00:00"))"#,
+                r#"duration("-P1D")"#,
+            ),
+            (
+                None,
+                r#"mean([duration("PT1S"), duration("PT2S")])"#,
+                r#"duration("PT1.500S")"#,
+            ),
+            (
+                None,
+                r#"min([duration("P3D"), duration("PT1H"), duration("P1D")])"#,
+                r#"duration("PT1H")"#,
+            ),
+            (
+                None,
+                r#"max([duration("P3D"), duration("PT1H"), duration("P1D")])"#,
+                r#"duration("P3D")"#,
+            ),
+            (None, "6 / 2", "3"),
+            (None, "1.5 * 2", "3"),
+            (None, "0.5 + 0.5", "1"),
+            (None, "sum([1.5, 1.5])", "3"),
+            (None, "decimal(3, 2)", "3.00"),
+            (None, "decimal(1.5, 0)", "2"),
+            (None, "1.50", "1.50"),
+            (None, "(2 ** 3) ** 2", "64"),
+            (None, "2 * 3 ** 2", "18"),
+            (None, "2 ** -1", "0.5"),
+            (None, "0.1 ** -30", "1000000000000000000000000000000"),
+            (None, "(0.1 ** -80) = 10 ** 80", "true"),
+            (None, "2 ** -2", "0.25"),
+            (None, "4 ** 0.5", "2"),
+            (None, r#"title case("hELLO wORLD")"#, r#""Hello World""#),
+            (None, r#"title case("a  b")"#, r#""A  B""#),
+            (None, r#"title case("élan vital")"#, r#""Élan Vital""#),
+            (
+                None,
+                r#"title case("ñANDÚ über ÅLAND")"#,
+                r#""Ñandú Über Åland""#,
+            ),
+            (None, r#"title case("mIxEd cAsE")"#, r#""Mixed Case""#),
+            (None, r#"title case("σΟΦΊΑ")"#, r#""Σοφία""#),
+            (None, r#"upper case("straße")"#, r#""STRASSE""#),
+            (None, r#"parse json("{\"a\": [1, 2]}").a[1]"#, "1"),
+            (
+                None,
+                r#"parse json("{\"b\": null, \"a\": [true, 1.5, \"x\"]}")"#,
+                r#"{"a":[true, 1.5, "x"], "b":null}"#,
+            ),
+            (None, r#"parse json("1e3")"#, "1000"),
+            (None, r#"parse json("null")"#, "null"),
+            (
+                None,
+                "to json string({a: 1, b: [2, 3]})",
+                r#""{\"a\":1,\"b\":[2,3]}""#,
+            ),
+            (
+                None,
+                r#"parse json(to json string({a: [1, "x"], b: {}}, true))"#,
+                r#"{"a":[1, "x"], "b":{}}"#,
+            ),
+            // chained calls, indexing and attributes
+            (None, "({a:{b:1}}).a.b", "1"),
+            (None, "[{x:5}][1].x", "5"),
+            (None, "{a:{b:{c:2}}}.a.b.c", "2"),
+            (None, "(function() {attr: 3})().attr", "3"),
+            (None, "(function() [4, 5, 6])()[2]", "5"),
+            (None, "{f: function() {a: 7}}.f().a", "7"),
+            (None, "[[1, 2], [3, 4]][2][1]", "3"),
+            (None, r#"{a: [{n: "x"}]}.a[1].n"#, r#""x""#),
+            (None, "differences([10, 13, 9])", "[3, -4]"),
+            (None, "differences([1.5, 2])", "[0.5]"),
+            (None, "differences([7])", "[]"),
+            (None, "differences([])", "[]"),
+            (None, "pairwise([1, 2, 4], function(a, b) b / a)", "[2, 2]"),
+            (
+                None,
+                r#"pairwise(["a", "b", "c"], function(a, b) a + b)"#,
+                r#"["ab", "bc"]"#,
+            ),
+            (None, "pairwise([1], function(a, b) a + b)", "[]"),
+            (None, "first index of([1,2,3,2], 5)", "null"),
+            (None, "first index of([], 5)", "null"),
+            (None, "first index of([{a:1},{a:2}], {a:2})", "2"),
+            (None, "first index of([[1],[1,2]], [1,2])", "2"),
+            // multibyte strings are counted and sliced by chars
+            (None, r#"string length("héllo")"#, "5"),
+            (None, r#"string length("中文")"#, "2"),
+            (None, r#"substring("héllo", 2)"#, r#""éllo""#),
+            (None, r#"substring("héllo", 2, 2)"#, r#""él""#),
+            (None, r#"substring("foobar", -3)"#, r#""bar""#),
+            (None, r#"substring("foobar", -3, 2)"#, r#""ba""#),
+            (None, r#"substring("foobar", -7)"#, r#""""#),
+            (None, r#"substring("foobar", 3, 100)"#, r#""obar""#),
+            (None, r#"substring("foobar", 3, 0)"#, r#""""#),
+            (None, r#"substring("中文字符", -2, 1)"#, r#""字""#),
+            (None, r#"code points("hé中")"#, r#"["h", "é", "中"]"#),
+            (None, r#"code points("")"#, "[]"),
+            (None, r#"slugify("Héllo World!")"#, r#""hello-world""#),
+            (None, r#"ascii fold("naïve café")"#, r#""naive cafe""#),
             // test context functions
             (None, r#"get value({"a": 5, b: 9}, "b")"#, "9"),
             (
@@ -1043,6 +2251,9 @@ mod test {
                 r#"get value({"a": 5, b: {"c k": {m: 5}}}, ["b", "c k", "m"])"#,
                 "5",
             ),
+            (None, r#"get value({a: {b: 5}}, "a.b")"#, "5"),
+            (None, r#"get value({a: {b: 5}}, "a.c")"#, "null"),
+            (None, r#"get value({"a.b": 7}, ["a.b"])"#, "7"),
             (
                 None,
                 r#"context put({"o":8}, ["a", "b", "c d"], 3)"#,
@@ -1160,6 +2371,7 @@ mod test {
             (Some(r#"{"?": 5}"#), r#">6, =8, < 3"#, "false"), // unary tests
             (Some(r#"{"?": 5}"#), r#">6, <8, < 3"#, "true"),
             (Some(r#"{"?": 5}"#), r#"?>6, ?<8, < 3"#, "true"),
+            (Some(r#"{"?": 5}"#), r#"< "a", 5"#, "true"), // incomparable test skipped
         ];
 
         for (ctx, input, output) in testcases {
@@ -1179,6 +2391,1367 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_range_endpoint_type() {
+        let mut eng = super::Engine::new();
+        let res = eng.parse_and_eval("[true..false]");
+        assert_matches!(
+            res,
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::ValueError(_),
+                pos: _
+            })
+        );
+    }
+
+    #[test]
+    fn test_deep_equals() {
+        let mut eng = super::Engine::new();
+        let a = eng.parse_and_eval("{a: [1, {b: 2}], c: null}").unwrap();
+        let b = eng.parse_and_eval("{c: null, a: [1.0, {b: 2}]}").unwrap();
+        let c = eng.parse_and_eval("{c: null, a: [1, {b: 3}]}").unwrap();
+        assert!(a.deep_equals(&b));
+        assert!(a.deep_equals(&a.clone()));
+        assert!(!a.deep_equals(&c));
+        assert_eq!(a == b, a.deep_equals(&b));
+    }
+
+    #[test]
+    fn test_type_descriptor() {
+        let mut eng = super::Engine::new();
+        let testcases = [
+            ("[1, 2, 3]", "list<number>"),
+            (r#"[1, "a"]"#, "list<any>"),
+            ("[]", "list<any>"),
+            ("[[1], [2, 3]]", "list<list<number>>"),
+            ("[[1], []]", "list<any>"),
+            (
+                r#"{a: 1, b: {c: ["x"], d: null}}"#,
+                "context<a: number, b: context<c: list<string>, d: null>>",
+            ),
+            ("[{a: 1}, {a: 2}]", "list<context<a: number>>"),
+            (r#""abc""#, "string"),
+        ];
+        for (input, output) in testcases {
+            let v = eng.parse_and_eval(input).unwrap();
+            assert_eq!(v.type_descriptor(), output, "input: {}", input);
+        }
+
+        // deeply nested structures are cut at the depth limit
+        let mut v = super::Value::NumberV(Numeric::ONE);
+        for _ in 0..100 {
+            v = super::Value::ArrayV(super::Rc::new(super::RefCell::new(vec![v])));
+        }
+        let desc = v.type_descriptor();
+        assert!(desc.starts_with("list<list<"));
+        assert!(desc.contains("list<list>"));
+    }
+
+    #[test]
+    fn test_argument_collision() {
+        let mut eng = super::Engine::new();
+        let testcases = [
+            "append([1], items: 2)",                   // named var arg
+            "append(2, list: [1])",                    // positional and named conflict
+            r#"substring(string: "abc", 2)"#,          // named then positional conflict
+            r#"substring("abc", 1, foo: 2)"#,          // unknown argument
+            "substring(start position: 1, length: 2)", // missing required argument
+        ];
+        for input in testcases {
+            assert_matches!(
+                eng.parse_and_eval(input),
+                Err(super::EvalError {
+                    kind: super::EvalErrorKind::ValueError(_),
+                    pos: _
+                }),
+                "input {}",
+                input
+            );
+        }
+        assert_eq!(
+            eng.parse_and_eval(r#"substring(start position: 2, string: "abc")"#)
+                .unwrap()
+                .to_string(),
+            r#""bc""#
+        );
+        assert_eq!(
+            eng.parse_and_eval("sublist(start position: 2, list: [1, 2, 3])")
+                .unwrap()
+                .to_string(),
+            "[2, 3]"
+        );
+    }
+
+    #[test]
+    fn test_write_json() {
+        let mut eng = super::Engine::new();
+        let v = eng
+            .parse_and_eval(r#"{b: [1, 2.50, "x\"y"], a: {c: null, d: true}, e: @"2023-09-17"}"#)
+            .unwrap();
+        let mut buf: Vec<u8> = vec![];
+        v.write_json(&mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            r#"{"a":{"c":null,"d":true},"b":[1,2.50,"x\"y"],"e":"2023-09-17"}"#
+        );
+
+        // a large array
+        let items: Vec<super::Value> = (0..100000).map(super::Value::from_usize).collect();
+        let v = super::ArrayV(super::Rc::new(super::RefCell::new(items)));
+        let mut buf: Vec<u8> = vec![];
+        v.write_json(&mut buf).unwrap();
+        let expected: Vec<String> = (0..100000).map(|i| i.to_string()).collect();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            format!("[{}]", expected.join(","))
+        );
+
+        let f = eng.parse_and_eval("function(a) a").unwrap();
+        assert!(f.write_json(&mut vec![]).is_err());
+    }
+
+    #[test]
+    fn test_bad_digit_separators() {
+        let mut eng = super::Engine::new();
+        for input in ["1__0", "1_", "1_.5", "1._5"] {
+            assert!(eng.parse_and_eval(input).is_err(), "{} should fail", input);
+        }
+        // `_1` is a name rather than a number
+        assert_matches!(
+            eng.parse_and_eval("_1"),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::VarNotFound(_),
+                pos: _
+            })
+        );
+    }
+
+    #[test]
+    fn test_dump_scopes() {
+        let mut eng = super::Engine::new();
+        eng.load_context_string("{a: 1}").unwrap();
+        eng.push_frame();
+        eng.bind_var("a".to_owned(), super::Value::NumberV(Numeric::from_i32(2)));
+        // the prelude frame, the context frame and the pushed frame
+        let scopes = eng.dump_scopes();
+        assert_eq!(scopes.len(), 3);
+        assert!(scopes[0].is_empty());
+        assert_eq!(scopes[1].get("a").unwrap().to_string(), "1");
+        assert_eq!(scopes[2].get("a").unwrap().to_string(), "2");
+        eng.pop_frame();
+        assert_eq!(eng.dump_scopes().len(), 2);
+    }
+
+    #[test]
+    fn test_strict_aggregation() {
+        let mut eng = super::Engine::new();
+        for input in [
+            r#"strict sum([1, "x"])"#,
+            "strict sum(31, -1, 9, false, -1, -99)",
+            "strict min(31, -1, 9, false)",
+            r#"strict max(1, "a")"#,
+            "strict mean(1, null)",
+            "strict stddev(1, [2])",
+        ] {
+            assert_matches!(
+                eng.parse_and_eval(input),
+                Err(super::EvalError {
+                    kind: super::EvalErrorKind::TypeError(_),
+                    pos: _
+                }),
+                "{} should be a type error",
+                input
+            );
+        }
+        let err = eng.parse_and_eval(r#"strict sum([1, "x"])"#).unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "TypeError: expect number at element[2], but string found"
+        );
+    }
+
+    #[test]
+    fn test_strict_conversion() {
+        let mut eng = super::Engine::new();
+        assert_eq!(
+            eng.parse_and_eval(r#"to number("1_000.5")"#)
+                .unwrap()
+                .to_string(),
+            "1000.5"
+        );
+        assert_eq!(
+            eng.parse_and_eval(r#"to date("2023-06-01")"#)
+                .unwrap()
+                .to_string(),
+            r#"date("2023-06-01")"#
+        );
+
+        let err = eng.parse_and_eval(r#"to number("12a")"#).unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            r#"ValueError: fail to parse number from "12a""#
+        );
+        let err = eng.parse_and_eval(r#"to date("2023-13-45")"#).unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            r#"ValueError: fail to parse date from "2023-13-45""#
+        );
+        assert_matches!(
+            eng.parse_and_eval("to number(true)"),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::ValueError(_),
+                pos: _
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_boolean() {
+        let mut eng = super::Engine::new();
+        for (input, expected) in [
+            ("to boolean(0)", "false"),
+            ("to boolean(2.5)", "true"),
+            (r#"to boolean("")"#, "false"),
+            (r#"to boolean("no")"#, "true"),
+            ("to boolean([])", "false"),
+            ("to boolean([0])", "true"),
+            ("to boolean(null)", "false"),
+            ("to boolean(true)", "true"),
+        ] {
+            let v = eng.parse_and_eval(input).unwrap();
+            assert_eq!(v.to_string(), expected, "input {}", input);
+        }
+
+        eng.set_dialect(super::Dialect::Dmn12);
+        for (input, expected) in [("to boolean(null)", "null"), ("to boolean(false)", "false")] {
+            let v = eng.parse_and_eval(input).unwrap();
+            assert_eq!(v.to_string(), expected, "input {}", input);
+        }
+        for input in ["to boolean(0)", r#"to boolean("yes")"#, "to boolean([1])"] {
+            assert_matches!(
+                eng.parse_and_eval(input),
+                Err(super::EvalError {
+                    kind: super::EvalErrorKind::TypeError(_),
+                    pos: _
+                }),
+                "input {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_duration_components() {
+        let mut eng = super::Engine::new();
+        for (input, expected) in [
+            (r#"@"P1Y2M".years"#, "1"),
+            (r#"@"P1Y2M".months"#, "2"),
+            (r#"@"P14M".years"#, "1"),
+            (r#"@"PT25H".hours"#, "1"),
+            (r#"@"PT25H".days"#, "1"),
+            (r#"@"P2DT3H4M5S".minutes"#, "4"),
+            (r#"@"P2DT3H4M5S".seconds"#, "5"),
+            (r#"@"P1Y2M".days"#, "0"),
+        ] {
+            let v = eng.parse_and_eval(input).unwrap();
+            assert_eq!(v.to_string(), expected, "input {}", input);
+        }
+        let err = eng.parse_and_eval(r#"@"P1D".weeks"#).unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "ValueError: duration has no component `weeks`"
+        );
+    }
+
+    #[test]
+    fn test_load_context_function_siblings() {
+        let mut eng = super::Engine::new();
+        eng.load_context_string("{a: 5, f: function() a}").unwrap();
+        assert_eq!(eng.parse_and_eval("f()").unwrap().to_string(), "5");
+
+        // siblings are found regardless of their order in the map
+        let mut eng = super::Engine::new();
+        eng.load_context_string("{f: function(x) g(x) + z, g: function(y) y * 2, z: 1}")
+            .unwrap();
+        assert_eq!(eng.parse_and_eval("f(3)").unwrap().to_string(), "7");
+    }
+
+    #[test]
+    fn test_datetime_difference() {
+        // only the sub-second part of the difference goes to the
+        // milliseconds, whole days carry no fraction of a second
+        let mut eng = super::Engine::new();
+        for (input, expected) in [
+            (
+                r#"@"2023-06-01T10:33:20+01:00" - @"2022-04-01T10:33:20+01:00""#,
+                r#"duration("P426D")"#,
+            ),
+            (
+                r#"(@"2023-06-01T10:33:20+01:00" - @"2022-04-01T10:33:20+01:00").seconds"#,
+                "0",
+            ),
+            (
+                r#"@"2020-01-01T00:00:00Z" - @"2020-01-01T01:30:15Z""#,
+                r#"duration("-PT1H30M15S")"#,
+            ),
+            (
+                r#"@"2020-03-01T00:00:00Z" - @"2020-01-01T00:00:00Z""#,
+                r#"duration("P60D")"#,
+            ),
+        ] {
+            let v = eng.parse_and_eval(input).unwrap();
+            assert_eq!(v.to_string(), expected, "input {}", input);
+        }
+    }
+
+    #[test]
+    fn test_temporal_properties() {
+        let mut eng = super::Engine::new();
+        for (input, expected) in [
+            (r#"@"2023-06-01".year"#, "2023"),
+            (r#"@"2023-06-01".month"#, "6"),
+            (r#"date("2023-06-01").day"#, "1"),
+            (r#"@"2023-06-01".weekday"#, "4"),
+            (r#"@"2023-06-01T10:33:20+01:00".hour"#, "10"),
+            (r#"@"2023-06-01T10:33:20+01:00".minute"#, "33"),
+            (r#"@"2023-06-01T10:33:20+01:00".second"#, "20"),
+            (r#"@"2023-06-01T10:33:20+01:00".month"#, "6"),
+            (
+                r#"@"2023-06-01T10:33:20+01:00".time offset"#,
+                r#"duration("PT1H")"#,
+            ),
+            (r#"time("08:15:30").minute"#, "15"),
+            (
+                r#"time("08:15:30-05:30").time offset"#,
+                r#"duration("-PT5H30M")"#,
+            ),
+        ] {
+            let v = eng.parse_and_eval(input).unwrap();
+            assert_eq!(v.to_string(), expected, "input {}", input);
+        }
+        let err = eng.parse_and_eval(r#"@"2023-06-01".hour"#).unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "ValueError: date has no property `hour`"
+        );
+        assert_eq!(
+            eng.parse_and_eval("{year: 1}.year").unwrap().to_string(),
+            "1"
+        );
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut eng = super::Engine::new();
+        eng.load_context_string("{y: 2}").unwrap();
+        let snapshot = eng.snapshot();
+        eng.parse_and_eval(r#"set("x", 1)"#).unwrap();
+        eng.parse_and_eval(r#"set("y", 3)"#).unwrap();
+        assert_eq!(eng.parse_and_eval("x + y").unwrap().to_string(), "4");
+
+        eng.restore(snapshot);
+        assert_eq!(
+            eng.parse_and_eval("is defined(x)").unwrap().to_string(),
+            "false"
+        );
+        assert_eq!(eng.parse_and_eval("y").unwrap().to_string(), "2");
+    }
+
+    #[test]
+    fn test_concat_non_string() {
+        let mut eng = super::Engine::new();
+        let err = eng.parse_and_eval(r#"concat("a", 1, "c")"#).unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "TypeError: expect string at argument[2], but number found"
+        );
+    }
+
+    #[test]
+    fn test_invert_error() {
+        let mut eng = super::Engine::new();
+        let err = eng.parse_and_eval("invert({a: [1]})").unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "TypeError: expect string, number or boolean at key `a`, but array found"
+        );
+    }
+
+    #[test]
+    fn test_flatten_cyclic_list() {
+        let mut eng = super::Engine::new();
+        let v = eng.parse_and_eval("[1]").unwrap();
+        if let super::ArrayV(a) = &v {
+            a.borrow_mut().push(v.clone());
+        }
+        eng.load_context(vec![("l".to_owned(), v.clone())]);
+        let err = eng.parse_and_eval("flatten(l)").unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "ValueError: flatten exceeds the max depth 64"
+        );
+        assert_eq!(
+            eng.parse_and_eval("count(flatten(l, 3))")
+                .unwrap()
+                .to_string(),
+            "5"
+        );
+        // break the cycle so that the list is dropped
+        if let super::ArrayV(a) = &v {
+            a.borrow_mut().clear();
+        }
+    }
+
+    #[test]
+    fn test_distinct_values_large_list() {
+        let mut eng = super::Engine::new();
+        let arr: Vec<super::Value> = (0..10000)
+            .map(|i| super::Value::from_usize(i % 100))
+            .collect();
+        eng.load_context(vec![(
+            "l".to_owned(),
+            super::ArrayV(std::rc::Rc::new(std::cell::RefCell::new(arr))),
+        )]);
+        let v = eng.parse_and_eval("distinct values(l)").unwrap();
+        let expected: Vec<super::Value> = (0..100).map(super::Value::from_usize).collect();
+        assert_eq!(
+            v,
+            super::ArrayV(std::rc::Rc::new(std::cell::RefCell::new(expected)))
+        );
+
+        // mixed lists go the linear path and agree with scalar lists
+        for (input, expected) in [
+            (
+                r#"distinct values([1, "a", 1.0, "a", null, null])"#,
+                r#"[1, "a", null]"#,
+            ),
+            (
+                r#"distinct values([1, {a: 1}, 1.0, {a: 1}, [2], [2]])"#,
+                r#"[1, {"a":1}, [2]]"#,
+            ),
+            ("union([1, 2, 1], [3, 2])", "[1, 2, 3]"),
+            ("union([[1], 2], [[1], 3])", "[[1], 2, 3]"),
+        ] {
+            let v = eng.parse_and_eval(input).unwrap();
+            assert_eq!(v.to_string(), expected, "input {}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval_scoped() {
+        let mut eng = super::Engine::new();
+        eng.set_var("a".to_owned(), super::Value::from_usize(1));
+        let depth = eng.dump_scopes().len();
+
+        let node = parse(r#"set("b", 2) + a"#, eng.as_box(), Default::default()).unwrap();
+        let v = eng.eval_scoped(node).unwrap();
+        assert_eq!(v.to_string(), "3");
+        assert!(eng.resolve("b".to_owned()).is_none());
+        assert_eq!(eng.dump_scopes().len(), depth);
+
+        let node = parse(r#"set("c", 2) + x.y"#, eng.as_box(), Default::default()).unwrap();
+        assert!(eng.eval_scoped(node).is_err());
+        assert!(eng.resolve("c".to_owned()).is_none());
+        assert_eq!(eng.dump_scopes().len(), depth);
+    }
+
+    #[test]
+    fn test_differences_non_number() {
+        let mut eng = super::Engine::new();
+        assert_matches!(
+            eng.parse_and_eval(r#"differences([1, "a"])"#),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::ValueError(_),
+                pos: _
+            })
+        );
+    }
+
+    #[test]
+    fn test_dotop_on_non_context() {
+        let mut eng = super::Engine::new();
+        let err = eng.parse_and_eval("(function() 5)().a").unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "RuntimeError: value number has no attribute `a`"
+        );
+    }
+
+    #[test]
+    fn test_parse_json_error() {
+        let mut eng = super::Engine::new();
+        let err = eng.parse_and_eval(r#"parse json("{\"a\": ")"#).unwrap_err();
+        assert_matches!(err.kind, super::EvalErrorKind::ValueError(_));
+        assert_eq!(
+            err.kind.to_string(),
+            "ValueError: fail to parse json: EOF while parsing a value at line 1 column 6"
+        );
+    }
+
+    #[test]
+    fn test_to_json_string() {
+        let mut eng = super::Engine::new();
+        let v = eng
+            .parse_and_eval(r#"{a: 1, b: [2, 3], c: [], d: {e: "x"}}"#)
+            .unwrap();
+        assert_eq!(
+            v.to_json_string(true).unwrap(),
+            "{\n  \"a\": 1,\n  \"b\": [\n    2,\n    3\n  ],\n  \"c\": [],\n  \"d\": {\n    \"e\": \"x\"\n  }\n}"
+        );
+
+        let v = eng
+            .parse_and_eval(r#"to json string([date("2023-06-01"), duration("PT1H"), [1..5)])"#)
+            .unwrap();
+        assert_eq!(
+            v.to_string(),
+            r#""[\"2023-06-01\",\"PT1H\",{\"end\":5,\"end included\":false,\"start\":1,\"start included\":true}]""#
+        );
+
+        assert_matches!(
+            eng.parse_and_eval("to json string([function(a) a])"),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::ValueError(_),
+                pos: _
+            })
+        );
+        assert_matches!(
+            eng.parse_and_eval(r#"to json string(1, "yes")"#),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::TypeError(_),
+                pos: _
+            })
+        );
+    }
+
+    #[test]
+    fn test_max_nesting_depth() {
+        let mut eng = super::Engine::new();
+        eng.set_max_depth(3);
+        assert_eq!(
+            eng.parse_and_eval("[[{a: 1}]]").unwrap().to_string(),
+            r#"[[{"a":1}]]"#
+        );
+        for input in [
+            "[[[[1]]]]",
+            "{a: {b: {c: {d: 1}}}}",
+            r#"context put({}, ["a", "b", "c"], [1])"#,
+            r#"context put({a: [[[2]]]}, "b", 1)"#,
+        ] {
+            let err = eng.parse_and_eval(input).unwrap_err();
+            assert_eq!(
+                err.kind.to_string(),
+                "ValueError: value exceeds the max nesting depth 3",
+                "input: {}",
+                input
+            );
+        }
+
+        // a loop nesting the context a level each round
+        let mut eng = super::Engine::new();
+        eng.set_max_depth(10);
+        let empty = eng.parse_and_eval("{}").unwrap();
+        eng.set_var("x".to_owned(), empty);
+        let mut res = Ok(super::NullV);
+        for _ in 0..20 {
+            res = eng.parse_and_eval(r#"set("x", context put({}, "child", x))"#);
+            if res.is_err() {
+                break;
+            }
+        }
+        assert_matches!(
+            res,
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::ValueError(_),
+                pos: _
+            })
+        );
+        assert_eq!(eng.resolve("x".to_owned()).unwrap().nesting_depth(100), 10);
+
+        // no limit by default
+        let mut eng = super::Engine::new();
+        assert!(eng.parse_and_eval("[[[[[[1]]]]]]").is_ok());
+    }
+
+    #[test]
+    fn test_power_errors() {
+        let mut eng = super::Engine::new();
+        let err = eng.parse_and_eval("0 ** -1").unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "ValueError: 0 ** -1 is not a finite number"
+        );
+        assert_matches!(
+            eng.parse_and_eval(r#""a" ** 2"#),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::TypeError(_),
+                pos: _
+            })
+        );
+    }
+
+    #[test]
+    fn test_set_var_typed() {
+        let mut eng = super::Engine::new();
+        let v = eng
+            .set_var_typed(
+                "n".to_owned(),
+                super::StrV("5".to_owned()),
+                ValueType::Number,
+            )
+            .unwrap();
+        assert_eq!(v.to_string(), "5");
+        assert_eq!(eng.parse_and_eval("n + 1").unwrap().to_string(), "6");
+        assert_eq!(eng.var_type("n"), Some(ValueType::Number));
+
+        // later sets to the slot are coerced as well
+        assert_eq!(
+            eng.parse_and_eval(r#"set("n", "7") + 1"#)
+                .unwrap()
+                .to_string(),
+            "8"
+        );
+
+        for input in [r#"set("n", "abc")"#, r#"set typed("m", [1], "number")"#] {
+            assert_matches!(
+                eng.parse_and_eval(input),
+                Err(super::EvalError {
+                    kind: super::EvalErrorKind::TypeError(_),
+                    pos: _
+                })
+            );
+        }
+        assert_eq!(eng.parse_and_eval("n").unwrap().to_string(), "7");
+        assert!(eng.resolve("m".to_owned()).is_none());
+
+        let err = eng
+            .set_var_typed("d".to_owned(), super::BoolV(true), ValueType::Date)
+            .unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "TypeError: expect date for `d`, found boolean"
+        );
+
+        let v = eng
+            .parse_and_eval(r#"set typed("d", "2023-06-01", "date")"#)
+            .unwrap();
+        assert_eq!(v.to_string(), r#"date("2023-06-01")"#);
+        assert_eq!(eng.var_type("d"), Some(ValueType::Date));
+
+        // untyped variables take any value
+        assert_eq!(
+            eng.parse_and_eval(r#"set("x", "abc")"#)
+                .unwrap()
+                .to_string(),
+            r#""abc""#
+        );
+        assert_eq!(eng.var_type("x"), None);
+    }
+
+    #[test]
+    fn test_duration_aggregation_errors() {
+        let mut eng = super::Engine::new();
+        for input in [
+            r#"strict sum([duration("P1D"), 2])"#,
+            r#"strict mean([duration("P1D"), "x"])"#,
+        ] {
+            assert_matches!(
+                eng.parse_and_eval(input),
+                Err(super::EvalError {
+                    kind: super::EvalErrorKind::TypeError(_),
+                    pos: _
+                })
+            );
+        }
+        assert_matches!(
+            eng.parse_and_eval(
+                r#"sum(duration("P1M"), date and time("2020-01-01T00:00:00") - date and time("2020-01-02T00:00:00"))"#
+            ),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::ValueError(ref msg),
+                pos: _
+            }) if msg.contains("different signs")
+        );
+    }
+
+    #[test]
+    fn test_replace_literal_empty_match() {
+        let mut eng = super::Engine::new();
+        for input in [
+            r#"replace literal("abc", "", "-")"#,
+            r#"replace first("abc", "", "-")"#,
+        ] {
+            assert_matches!(
+                eng.parse_and_eval(input),
+                Err(super::EvalError {
+                    kind: super::EvalErrorKind::ValueError(_),
+                    pos: _
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_deep_path_errors() {
+        let mut eng = super::Engine::new();
+        for input in [
+            r#"deep get({a: 1}, "a..b")"#,
+            r#"deep get({a: 1}, "")"#,
+            r#"deep put({a: 1}, "a.", 2)"#,
+            r#"deep get({a: 1}, "a\\x")"#,
+        ] {
+            assert_matches!(
+                eng.parse_and_eval(input),
+                Err(super::EvalError {
+                    kind: super::EvalErrorKind::ValueError(_),
+                    pos: _
+                }),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        let mut eng = super::Engine::new();
+        for input in [
+            "1 / 0",
+            "1.5 / 0.0",
+            "5 % 0",
+            "5.5 mod 0",
+            "modulo(5, 0)",
+            "sum([1, 2]) / 0",
+        ] {
+            let err = eng.parse_and_eval(input).unwrap_err();
+            assert_eq!(
+                err.kind.to_string(),
+                "ValueError: division by zero",
+                "input: {}",
+                input
+            );
+        }
+        eng.set_precision(2);
+        assert_eq!(
+            eng.parse_and_eval("1 / 0").unwrap_err().kind.to_string(),
+            "ValueError: division by zero"
+        );
+
+        // products grow instead of overflowing
+        let v = eng.parse_and_eval("99999999999 * 99999999999").unwrap();
+        assert_eq!(v.to_string(), "9999999999800000000001");
+    }
+
+    #[test]
+    fn test_decimal_point_errors() {
+        let mut eng = super::Engine::new();
+        let err = eng.parse_and_eval("5.").unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "ScanError: number 5. has no digits after the decimal point"
+        );
+        let err = eng.parse_and_eval("5..6").unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "ParseError: a range must be enclosed in brackets, e.g. [1..5]"
+        );
+        assert_matches!(
+            eng.parse_and_eval("1 2"),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::Parse(_),
+                pos: _
+            })
+        );
+    }
+
+    #[test]
+    fn test_event_hook() {
+        let events: super::Rc<super::RefCell<Vec<String>>> =
+            super::Rc::new(super::RefCell::new(vec![]));
+        let mut eng = super::Engine::new();
+        let collected = events.clone();
+        eng.set_event_hook(Box::new(move |event| {
+            collected.borrow_mut().push(event.to_string())
+        }));
+        eng.set_var("a".to_owned(), super::NumberV(Numeric::from_i32(2)));
+        let v = eng.parse_and_eval("(function(x) x * a)(abs(-3))").unwrap();
+        assert_eq!(v.to_string(), "6");
+        assert_matches!(eng.parse_and_eval("a + b"), Err(_));
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                "set a = 2",
+                "call (function [x] (* x a))",
+                "call abs",
+                "bind x = 3",
+                "error VarNotFound: `b`",
+            ]
+        );
+
+        // restoring the captured variables of a closure is not a bind
+        events.borrow_mut().clear();
+        eng.push_frame();
+        eng.bind_var("c".to_owned(), super::NumberV(Numeric::from_i32(1)));
+        let func = eng.parse_and_eval("function(x) x + c").unwrap();
+        eng.pop_frame();
+        events.borrow_mut().clear();
+        let v = eng
+            .call_function(&func, vec![super::NumberV(Numeric::from_i32(2))])
+            .unwrap();
+        assert_eq!(v.to_string(), "3");
+        assert_eq!(*events.borrow(), vec!["bind x = 2"]);
+
+        // nothing is reported once the hook is cleared
+        events.borrow_mut().clear();
+        eng.clear_event_hook();
+        assert_matches!(eng.parse_and_eval("b"), Err(_));
+        assert!(events.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_trace_events() {
+        let events: super::Rc<super::RefCell<Vec<String>>> =
+            super::Rc::new(super::RefCell::new(vec![]));
+        let mut eng = super::Engine::new();
+        let collected = events.clone();
+        eng.set_event_hook(Box::new(move |event| {
+            if let super::EngineEvent::Trace(..) = event {
+                collected.borrow_mut().push(event.to_string())
+            }
+        }));
+        eng.parse_and_eval("1 + 2").unwrap();
+        assert!(events.borrow().is_empty());
+
+        eng.set_trace(true);
+        let v = eng.parse_and_eval("1 + 2").unwrap();
+        assert_eq!(v.to_string(), "3");
+        let traces = events.borrow();
+        assert_eq!(traces.len(), 3, "unexpected traces {:?}", traces);
+        assert!(traces[0].starts_with("trace BinOp (+ 1 2) at "));
+        assert!(traces[1].starts_with("trace Number 1 at "));
+        assert!(traces[2].starts_with("trace Number 2 at "));
+    }
+
+    #[test]
+    fn test_closure_captures_referred_vars() {
+        let mut eng = super::Engine::new();
+        eng.push_frame();
+        eng.bind_var("a".to_owned(), super::NumberV(Numeric::from_i32(1)));
+        eng.bind_var("b".to_owned(), super::NumberV(Numeric::from_i32(2)));
+        eng.push_frame();
+        eng.bind_var("a".to_owned(), super::NumberV(Numeric::from_i32(3)));
+        let func = eng
+            .parse_and_eval("function(x) for y in [x] return y + a")
+            .unwrap();
+        eng.pop_frame();
+        eng.pop_frame();
+        let super::FuncV { closure, .. } = &func else {
+            panic!("expect a function, found {}", func);
+        };
+        // only the innermost `a` is captured, `b` is not referred
+        assert_eq!(closure.len(), 1);
+        assert_eq!(closure.get("a").unwrap().to_string(), "3");
+        let v = eng
+            .call_function(&func, vec![super::NumberV(Numeric::from_i32(2))])
+            .unwrap();
+        assert_eq!(v.to_string(), "[5]");
+    }
+
+    #[test]
+    fn test_call_function_empty_var_arg() {
+        let mut eng = super::Engine::new();
+        for (name, output) in [("count", "0"), ("concatenate", "[]"), ("union", "[]")] {
+            let func = eng.resolve(name.to_owned()).unwrap();
+            let v = eng.call_function(&func, vec![]).unwrap();
+            assert_eq!(v.to_string(), output, "calling {}", name);
+        }
+    }
+
+    #[test]
+    fn test_map_leaves() {
+        let mut eng = super::Engine::new();
+        let v = eng
+            .parse_and_eval(r#"{name: "ann", tags: ["a", 1, {b: "c"}], n: 2.5, ok: true}"#)
+            .unwrap();
+        let upper = |v: &super::Value| match v {
+            super::StrV(s) => super::StrV(s.to_uppercase()),
+            _ => v.clone(),
+        };
+        let mapped = v.map_leaves(&upper);
+        assert_eq!(
+            mapped.to_string(),
+            r#"{"n":2.5, "name":"ANN", "ok":true, "tags":["A", 1, {"b":"C"}]}"#
+        );
+        // the original is untouched
+        assert_eq!(
+            v.to_string(),
+            r#"{"n":2.5, "name":"ann", "ok":true, "tags":["a", 1, {"b":"c"}]}"#
+        );
+
+        // a shared list is mapped once and a list containing itself
+        // does not recurse forever
+        let shared = super::Rc::new(super::RefCell::new(vec![super::StrV("x".to_owned())]));
+        let v = super::ArrayV(super::Rc::new(super::RefCell::new(vec![
+            super::ArrayV(shared.clone()),
+            super::ArrayV(shared.clone()),
+        ])));
+        let mapped = v.map_leaves(&upper);
+        assert_eq!(mapped.to_string(), r#"[["X"], ["X"]]"#);
+        let super::ArrayV(items) = mapped else {
+            panic!("expect a list")
+        };
+        match (&items.borrow()[0], &items.borrow()[1]) {
+            (super::ArrayV(a), super::ArrayV(b)) => assert!(super::Rc::ptr_eq(a, b)),
+            _ => panic!("expect lists"),
+        }
+
+        shared.borrow_mut().push(super::ArrayV(shared.clone()));
+        let super::ArrayV(cyclic) = super::ArrayV(shared.clone()).map_leaves(&upper) else {
+            panic!("expect a list")
+        };
+        assert_eq!(cyclic.borrow()[0].to_string(), r#""X""#);
+        assert_matches!(&cyclic.borrow()[1], super::ArrayV(inner) if super::Rc::ptr_eq(inner, &shared));
+        // break the cycle so the list can be dropped
+        shared.borrow_mut().pop();
+    }
+
+    #[test]
+    fn test_min_max_incomparable() {
+        let mut eng = super::Engine::new();
+        for input in [
+            r#"min([1, date("2022-01-01")])"#,
+            r#"max(date("2022-01-01"), 1)"#,
+            r#"max([1, "a"])"#,
+            r#"min([@"P1M", @"PT1H"])"#,
+        ] {
+            let err = eng.parse_and_eval(input).unwrap_err();
+            assert_matches!(err.kind, super::EvalErrorKind::TypeError(_), "{}", input);
+        }
+        let err = eng
+            .parse_and_eval(r#"min([1, date("2022-01-01")])"#)
+            .unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "TypeError: expect values comparable with number at element[2], but date found"
+        );
+    }
+
+    #[test]
+    fn test_stddev_sample_errors() {
+        let mut eng = super::Engine::new();
+        let err = eng.parse_and_eval("stddev([5], sample: true)").unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "ValueError: sample stddev needs at least 2 numbers, found 1"
+        );
+        let err = eng
+            .parse_and_eval(r#"stddev([1, 2], sample: "yes")"#)
+            .unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "TypeError: expect argument `sample` to be boolean, found string"
+        );
+        let err = eng.parse_and_eval("stddev([], sample: true)").unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "ValueError: sample stddev needs at least 2 numbers, found 0"
+        );
+        // a positional argument still goes to the list
+        let v = eng.parse_and_eval("stddev(true, 1, 3)").unwrap();
+        assert_eq!(v.to_string(), "1");
+        // so a positional flag after a list is rejected
+        let err = eng.parse_and_eval("stddev([1, 2, 3], true)").unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "ValueError: argument `list`, a list cannot be mixed with other positional values, pass `sample` by name"
+        );
+    }
+
+    #[test]
+    fn test_with_prelude() {
+        let mut prelude = crate::prelude::PRELUDE.clone();
+        prelude.remove("sort");
+        prelude.add_native_func("double", &["n"], |_, args| -> super::EvalResult {
+            let n = args.get(&"n".to_owned()).unwrap();
+            Ok((n.clone() + n.clone())?)
+        });
+        let mut eng = super::Engine::with_prelude(prelude);
+        assert!(!eng.has_name("sort".to_owned()));
+        let err = eng.parse_and_eval("sort([2, 1])").unwrap_err();
+        assert_eq!(err.kind.to_string(), "VarNotFound: `sort`");
+        let v = eng.parse_and_eval("reverse([1, double(2)])").unwrap();
+        assert_eq!(v.to_string(), "[4, 1]");
+
+        // the default engine keeps the global prelude
+        let mut eng = super::Engine::new();
+        assert_eq!(
+            eng.parse_and_eval("sort([2, 1])").unwrap().to_string(),
+            "[1, 2]"
+        );
+        assert!(!eng.has_name("double".to_owned()));
+    }
+
+    #[test]
+    fn test_optional_and_var_args() {
+        let mut prelude = crate::prelude::PRELUDE.clone();
+        prelude.add_native_func_with_optional_args(
+            "tagged",
+            &[],
+            &["tag"],
+            Some("items"),
+            |_, args| -> super::EvalResult {
+                let tag = args.get(&"tag".to_owned()).cloned().unwrap_or(super::NullV);
+                let items = args.get(&"items".to_owned()).unwrap();
+                Ok(super::StrV(format!("{} {}", tag, items)))
+            },
+        );
+        let mut eng = super::Engine::with_prelude(prelude);
+        // the positional args fill the optional args before the var arg
+        let v = eng.parse_and_eval(r#"tagged("a", 1, 2)"#).unwrap();
+        assert_eq!(v.to_string(), r#""\"a\" [1, 2]""#);
+        let v = eng.parse_and_eval(r#"tagged(tag: "a")"#).unwrap();
+        assert_eq!(v.to_string(), r#""\"a\" []""#);
+        let v = eng.parse_and_eval("tagged()").unwrap();
+        assert_eq!(v.to_string(), r#""null []""#);
+
+        // while the named args of stddev are never positional
+        let v = eng.parse_and_eval("stddev").unwrap();
+        assert_eq!(v.to_string(), "function stddev(list..., sample?)");
+        let v = eng.parse_and_eval("stddev(true, 1, 3)").unwrap();
+        assert_eq!(v.to_string(), "1");
+        let err = eng.parse_and_eval("stddev(1, 3, size: 2)").unwrap_err();
+        assert_eq!(err.kind.to_string(), "ValueError: unknown argument size");
+    }
+
+    #[test]
+    fn test_dmn12_dialect() {
+        // (input, loose result, DMN 1.2 result)
+        let testcases = [
+            // three-valued logic
+            ("true and null", "false", "null"),
+            ("false and null", "false", "false"),
+            ("null and false", "false", "false"),
+            ("true and 1", "true", "null"),
+            ("null or true", "true", "true"),
+            ("false or null", "false", "null"),
+            ("false or false", "false", "false"),
+            // quantified expressions give booleans
+            ("some x in [1, 2] satisfies x > 1", "2", "true"),
+            ("some x in [1, 2] satisfies x > 5", "null", "false"),
+            ("every x in [1, 2] satisfies x > 0", "[1, 2]", "true"),
+            ("every x in [1, 2] satisfies x > 1", "[2]", "false"),
+            ("every x in [] satisfies x > 1", "[]", "true"),
+            ("some x in [1] satisfies x", "1", "false"),
+            // only true passes as a condition
+            ("if 1 then 2 else 3", "2", "3"),
+            ("if null then 2 else 3", "3", "3"),
+            ("if true then 2 else 3", "2", "2"),
+            // empty aggregations
+            ("sum([])", "0", "null"),
+            ("product([])", "1", "null"),
+            ("sum([1, 2])", "3", "3"),
+            ("product([2, 3])", "6", "6"),
+            ("all([])", "true", "true"),
+            ("any([])", "false", "false"),
+            ("all([true, false])", "false", "false"),
+            ("all([true, 1])", "true", "null"),
+            ("any([false, null])", "false", "null"),
+            ("any([null, true])", "true", "true"),
+        ];
+        for (input, loose, dmn12) in testcases {
+            let mut eng = super::Engine::new();
+            let v = eng.parse_and_eval(input).unwrap();
+            assert_eq!(v.to_string(), loose, "loose input {}", input);
+            eng.set_dialect(super::Dialect::Dmn12);
+            let v = eng.parse_and_eval(input).unwrap();
+            assert_eq!(v.to_string(), dmn12, "DMN 1.2 input {}", input);
+        }
+    }
+
+    #[test]
+    fn test_logic_short_circuit() {
+        let testcases = [
+            (r#"(true or set("x", 1), is defined(x))"#, "false"),
+            (r#"(false or set("x", 1), is defined(x))"#, "true"),
+            (r#"(false and set("x", 1), is defined(x))"#, "false"),
+            (r#"(true and set("x", 1), is defined(x))"#, "true"),
+            (r#"(false and set("x", 1), false or set("x", 2), x)"#, "2"),
+        ];
+        for (input, output) in testcases {
+            // a new engine for each case to start without x
+            let mut eng = super::Engine::new();
+            let v = eng.parse_and_eval(input).unwrap();
+            assert_eq!(v.to_string(), output, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_precision() {
+        let mut eng = super::Engine::new();
+        let v = eng.parse_and_eval("10 / 3").unwrap();
+        assert_eq!(v.to_string(), "3.3333333333333333333333333333333333");
+
+        eng.set_precision(5);
+        let v = eng.parse_and_eval("10 / 3").unwrap();
+        assert_eq!(v.to_string(), "3.33333");
+        let v = eng.parse_and_eval("20 / 3 + 1").unwrap();
+        assert_eq!(v.to_string(), "7.66667");
+        let v = eng.parse_and_eval("7 / 2").unwrap();
+        assert_eq!(v.to_string(), "3.5");
+    }
+
+    #[test]
+    fn test_coerce_unknown_type() {
+        let mut eng = super::Engine::new();
+        assert_matches!(
+            eng.parse_and_eval(r#"coerce(1, "colour")"#),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::ValueError(_),
+                pos: _
+            })
+        );
+    }
+
+    #[test]
+    fn test_explicit_question_mark_tests() {
+        let testcases = [
+            ("7", "? > 5, ? < 3", "true"),
+            ("4", "? > 5, ? < 3", "false"),
+            ("2", "> 5, ? < 3", "true"),
+            ("4", "? >= 4 and ? <= 6", "true"),
+            ("4", "? * 2 = 8", "true"),
+        ];
+        for (value, input, output) in testcases {
+            let mut eng = super::Engine::new();
+            let v = eng.parse_and_eval(value).unwrap();
+            eng.bind_var("?".to_owned(), v);
+            let res = eng.parse_and_eval_unary_tests(input).unwrap();
+            assert_eq!(
+                res.to_string(),
+                output,
+                "input: {} with ? = {}",
+                input,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_function_display() {
+        let mut eng = super::Engine::new();
+        let v = eng.parse_and_eval("function(x) x + 1").unwrap();
+        assert_eq!(v.to_string(), "function(x) x + 1");
+
+        let v = eng.parse_and_eval("{f: function(a, b) a + b}").unwrap();
+        assert_eq!(v.to_string(), r#"{"f":function(a, b) a + b}"#);
+
+        let v = eng.parse_and_eval("abs").unwrap();
+        assert_eq!(v.to_string(), "function abs(n)");
+
+        let v = eng.parse_and_eval("substring").unwrap();
+        assert_eq!(
+            v.to_string(),
+            "function substring(string, start position, length?)"
+        );
+
+        let v = eng.parse_and_eval("is defined").unwrap();
+        assert_eq!(v.to_string(), "function is defined(value)");
+    }
+
+    #[test]
+    fn test_engine_reset() {
+        let mut eng = super::Engine::new();
+        eng.load_context_string("{a: 1, double: function(x) x * 2}")
+            .unwrap();
+        eng.set_var("b".to_owned(), super::Value::from_usize(2));
+        assert_eq!(
+            eng.parse_and_eval("double(a + b)").unwrap().to_string(),
+            "6"
+        );
+
+        eng.reset_keep_functions();
+        assert!(!eng.has_name("a".to_owned()));
+        assert!(!eng.has_name("b".to_owned()));
+        assert_eq!(eng.parse_and_eval("double(4)").unwrap().to_string(), "8");
+
+        eng.reset();
+        assert!(!eng.has_name("double".to_owned()));
+        assert_matches!(
+            eng.parse_and_eval("a"),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::VarNotFound(_),
+                pos: _
+            })
+        );
+        // built-ins remain
+        assert_eq!(eng.parse_and_eval("abs(-3)").unwrap().to_string(), "3");
+    }
+
+    #[test]
+    fn test_between_type_mismatch() {
+        let mut eng = super::Engine::new();
+        let res = eng.parse_and_eval(r#"5 between "a" and 10"#);
+        assert_matches!(
+            res,
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::TypeError(_),
+                pos: _
+            })
+        );
+    }
+
+    #[test]
+    fn test_sublist_out_of_range() {
+        let mut eng = super::Engine::new();
+        for input in [
+            "sublist([1,2,3,4], -5)",
+            "sublist([1,2,3,4], 5)",
+            "sublist([1,2], 0)",
+            "[1, 2, 3][99999999999999999999]",
+            "[1, 2, 3][1.5]",
+            "[1, 2, 3][0]",
+        ] {
+            assert_matches!(
+                eng.parse_and_eval(input),
+                Err(super::EvalError {
+                    kind: super::EvalErrorKind::IndexError,
+                    pos: _
+                }),
+                "input {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_huge_integer_argument() {
+        let mut eng = super::Engine::new();
+        for input in [
+            "sublist([1, 2, 3], 1, 99999999999999999999)",
+            "sublist([1, 2, 3], 99999999999999999999)",
+        ] {
+            assert_matches!(
+                eng.parse_and_eval(input),
+                Err(super::EvalError {
+                    kind: super::EvalErrorKind::ValueError(_) | super::EvalErrorKind::IndexError,
+                    pos: _
+                }),
+                "input {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_run_length_decode_errors() {
+        let mut eng = super::Engine::new();
+        for input in [
+            "run length decode([{value: 1, count: 1000001}])",
+            "run length decode([{value: 1, count: 600000}, {value: 2, count: 400001}])",
+        ] {
+            let err = eng.parse_and_eval(input).unwrap_err();
+            assert_eq!(
+                err.kind.to_string(),
+                "ValueError: run length decode exceeds the max length 1000000",
+                "input {}",
+                input
+            );
+        }
+        let v = eng
+            .parse_and_eval("count(run length decode([{value: 1, count: 1000000}]))")
+            .unwrap();
+        assert_eq!(v.to_string(), "1000000");
+    }
+
+    #[test]
+    fn test_log_errors() {
+        let mut eng = super::Engine::new();
+        for input in [
+            "log(-1)",
+            "log(0)",
+            "log10(-5)",
+            "log2(0)",
+            "log(8, 1)",
+            "log(8, -2)",
+            "log(10 ** 400)",
+            "log10(10 ** 400)",
+            "log2(10 ** 400)",
+            "log(10 ** 400, 10)",
+            "log(10 ** -400)",
+        ] {
+            assert_matches!(
+                eng.parse_and_eval(input),
+                Err(super::EvalError {
+                    kind: super::EvalErrorKind::ValueError(_),
+                    pos: _
+                }),
+                "input {}",
+                input
+            );
+        }
+        let err = eng.parse_and_eval("log(-1)").unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "ValueError: argument[1] `number`, expect positive number, but -1 found"
+        );
+        let err = eng.parse_and_eval("log10(10 ** 400)").unwrap_err();
+        assert_eq!(
+            err.kind.to_string(),
+            "ValueError: argument[1] `number`, the logarithm is not a finite number"
+        );
+    }
+
+    #[test]
+    fn test_percentile_errors() {
+        let mut eng = super::Engine::new();
+        assert_matches!(
+            eng.parse_and_eval("percentile([], 0.5)"),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::ValueError(_),
+                pos: _
+            })
+        );
+        assert_matches!(
+            eng.parse_and_eval("percentile([1, 2], 1.5)"),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::ValueError(_),
+                pos: _
+            })
+        );
+    }
+
+    #[test]
+    fn test_hash_value() {
+        let mut eng = super::Engine::new();
+        let testcases = [
+            (
+                r#"hash value({a: 1, b: [2, "x"]})"#,
+                r#"hash value({b: [2, "x"], a: 1})"#,
+            ),
+            ("hash value(1)", "hash value(1.00)"),
+            ("hash value([1..5])", "hash value([1..5])"),
+        ];
+        for (input, other) in testcases {
+            let h1 = eng.parse_and_eval(input).unwrap();
+            let h2 = eng.parse_and_eval(other).unwrap();
+            assert_eq!(h1, h2, "hash mismatch {} and {}", input, other);
+        }
+
+        let h1 = eng.parse_and_eval("hash value({a: 1})").unwrap();
+        let h2 = eng.parse_and_eval("hash value({a: 2})").unwrap();
+        assert_ne!(h1, h2);
+        let h3 = eng.parse_and_eval(r#"hash value("1")"#).unwrap();
+        let h4 = eng.parse_and_eval("hash value(1)").unwrap();
+        assert_ne!(h3, h4);
+
+        assert_matches!(
+            eng.parse_and_eval("hash value(function(a) a)"),
+            Err(super::EvalError {
+                kind: super::EvalErrorKind::TypeError(_),
+                pos: _
+            })
+        );
+    }
+
     #[test]
     fn test_def_vars() {
         let mut eng = super::Engine::new();
@@ -1,9 +1,12 @@
-use core::hash::Hash;
+use core::hash::{Hash, Hasher};
 use core::slice::Iter;
 use std::cmp;
 
 use std::collections::{BTreeMap, HashSet};
 use std::fmt;
+use std::io;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
 
 #[inline(always)]
 pub fn compare_value<T>(a: T, b: T) -> cmp::Ordering
@@ -110,6 +113,131 @@ pub fn escape(input: &str) -> String {
     res
 }
 
+/// strip diacritics by decomposing the string and dropping the
+/// combining marks, e.g. "é" becomes "e"
+pub fn ascii_fold(input: &str) -> String {
+    input.nfd().filter(|c| !is_combining_mark(*c)).collect()
+}
+
+/// lowercase the diacritics-stripped string and join the alphanumeric
+/// runs by hyphens, e.g. "Héllo World!" becomes "hello-world"
+pub fn slugify(input: &str) -> String {
+    let mut res = String::new();
+    for c in ascii_fold(input).chars() {
+        if c.is_alphanumeric() {
+            res.extend(c.to_lowercase());
+        } else if !res.is_empty() && !res.ends_with('-') {
+            res.push('-');
+        }
+    }
+    res.trim_end_matches('-').to_owned()
+}
+
+#[test]
+fn test_slugify() {
+    assert_eq!(ascii_fold("Crème Brûlée"), "Creme Brulee");
+    assert_eq!(slugify("Héllo World!"), "hello-world");
+    assert_eq!(slugify("  --a__b--  "), "a-b");
+    assert_eq!(slugify("!!!"), "");
+}
+
+/// uppercase the first letter of each whitespace separated word and
+/// lowercase the rest, the whitespace is kept as is. the Unicode
+/// default casing is used regardless of the locale
+pub fn title_case(input: &str) -> String {
+    let mut res = String::with_capacity(input.len());
+    let mut word_start = true;
+    for c in input.chars() {
+        if c.is_whitespace() {
+            res.push(c);
+            word_start = true;
+        } else if word_start {
+            res.extend(c.to_uppercase());
+            word_start = false;
+        } else {
+            res.extend(c.to_lowercase());
+        }
+    }
+    res
+}
+
+#[test]
+fn test_title_case() {
+    assert_eq!(title_case("hELLO wORLD"), "Hello World");
+    assert_eq!(
+        title_case("  two   spaces\tand tab "),
+        "  Two   Spaces\tAnd Tab "
+    );
+    assert_eq!(title_case("élan ÉCOLE über"), "Élan École Über");
+    assert_eq!(title_case(""), "");
+}
+
+/// write a string as a quoted JSON string
+pub fn write_json_string<W: io::Write>(writer: &mut W, input: &str) -> io::Result<()> {
+    writer.write_all(b"\"")?;
+    for c in input.chars() {
+        match c {
+            '"' => writer.write_all(b"\\\"")?,
+            '\\' => writer.write_all(b"\\\\")?,
+            '\n' => writer.write_all(b"\\n")?,
+            '\r' => writer.write_all(b"\\r")?,
+            '\t' => writer.write_all(b"\\t")?,
+            c if (c as u32) < 0x20 => write!(writer, "\\u{:04x}", c as u32)?,
+            c => {
+                let mut buf = [0u8; 4];
+                writer.write_all(c.encode_utf8(&mut buf).as_bytes())?
+            }
+        }
+    }
+    writer.write_all(b"\"")
+}
+
+#[test]
+fn test_write_json_string() {
+    let mut buf: Vec<u8> = vec![];
+    write_json_string(&mut buf, "a\"b\\c\nd\u{1}é").unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), r#""a\"b\\c\nd\u0001é""#);
+}
+
+/// FNV-1a hasher, unlike the std DefaultHasher the result is
+/// stable across runs and rust versions
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    pub fn new() -> StableHasher {
+        StableHasher(Self::OFFSET_BASIS)
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+}
+
+#[test]
+fn test_stable_hasher() {
+    let mut hasher = StableHasher::new();
+    hasher.write(b"hello");
+    assert_eq!(hasher.finish(), 0xa430d84680aabd0b);
+}
+
 #[test]
 fn test_string_escape_unescape() {
     let input = "abc\tdef\r\nte\"ck";
@@ -1,10 +1,11 @@
-use crate::ast::{FuncCallArg, MapNodeItem, Node, NodeSyntax::*, VarValue};
+use crate::ast::{FuncCallArg, IterBinding, MapNodeItem, Node, NodeSyntax::*, VarValue};
 use crate::eval::Engine;
 use crate::helpers::find_duplicate;
 use crate::scan::{ScanError, Scanner, TextPosition, Token};
 use clap::ValueEnum;
 
 use std::backtrace::Backtrace;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
 
@@ -59,6 +60,8 @@ type NodeResult = Result<Box<Node>, ParseError>;
 pub struct Parser<'a> {
     scanner: Box<Scanner<'a>>,
     engine: Box<Engine>,
+    // prefixes of the known compound names, computed on first use
+    name_prefixes: Option<HashSet<String>>,
 }
 
 // shortcuts to go ahead one token
@@ -74,6 +77,7 @@ impl Parser<'_> {
         Parser {
             scanner: Box::new(scanner),
             engine,
+            name_prefixes: None,
         }
     }
 
@@ -107,11 +111,80 @@ impl Parser<'_> {
     pub fn parse(&mut self, top: ParseTop) -> NodeResult {
         goahead!(self);
         match top {
-            ParseTop::Expression => self.parse_expression(),
+            ParseTop::Expression => {
+                let node = self.parse_expression()?;
+                if self.scanner.expect("..") {
+                    return Err(ParseError::new(
+                        "a range must be enclosed in brackets, e.g. [1..5]".to_owned(),
+                    ));
+                } else if !self.scanner.expect("eof") {
+                    return Err(self.unexpect("eof"));
+                }
+                Ok(node)
+            }
             ParseTop::UnaryTests => self.parse_unary_tests(),
         }
     }
 
+    // parse statements separated by ';', a malformed statement is
+    // recorded and parsing resumes after the next ';', so that several
+    // errors can be reported in one pass
+    pub fn parse_all(&mut self) -> (Option<Box<Node>>, Vec<(ParseError, TextPosition)>) {
+        let mut elements = Vec::new();
+        let mut errors = Vec::new();
+        let start_pos = self.scanner.current_position();
+        if let Err(err) = self.scanner.next_token() {
+            errors.push((err.into(), self.scanner.current_position()));
+            self.resync();
+        }
+        while !self.scanner.expect("eof") {
+            match self.parse_statement() {
+                Ok(elem) => elements.push(elem),
+                Err(err) => {
+                    errors.push((err, self.scanner.current_position()));
+                    self.resync();
+                }
+            }
+        }
+        if !errors.is_empty() || elements.is_empty() {
+            (None, errors)
+        } else if elements.len() == 1 {
+            (elements.pop(), errors)
+        } else {
+            (Some(Node::new(ExprList(elements), start_pos)), errors)
+        }
+    }
+
+    fn parse_statement(&mut self) -> NodeResult {
+        let elem = self.parse_expression()?;
+        if self.scanner.expect(";") {
+            goahead!(self); // skip ';'
+        } else if !self.scanner.expect("eof") {
+            return Err(self.unexpect("';'"));
+        }
+        Ok(elem)
+    }
+
+    // skip tokens until the one after the next ';' or the end of input,
+    // characters which fail to scan are skipped too
+    fn resync(&mut self) {
+        loop {
+            if self.scanner.expect("eof") {
+                return;
+            }
+            if self.scanner.expect(";") {
+                if self.scanner.next_token().is_err() {
+                    self.scanner.skip_char();
+                    continue;
+                }
+                return;
+            }
+            if self.scanner.next_token().is_err() {
+                self.scanner.skip_char();
+            }
+        }
+    }
+
     fn parse_unary_tests(&mut self) -> NodeResult {
         let start_pos = self.scanner.current_token().position;
         let elem = self.parse_unary_test()?;
@@ -126,6 +199,15 @@ impl Parser<'_> {
             }
             //Ok(Node::new(UnaryTests(elements), start_pos))
         }
+        // tests are separated by ',' only, reject whitespace or newline
+        // separated entries instead of dropping all but the first
+        if !self.scanner.expect("eof") {
+            return Err(ParseError::new(format!(
+                "unexpected token {} '{}', unary tests must be separated by ','",
+                self.scanner.current_token().kind,
+                self.scanner.current_token().value,
+            )));
+        }
         Ok(Node::new(UnaryTests(elements), start_pos))
     }
 
@@ -137,7 +219,9 @@ impl Parser<'_> {
             let op = self.scanner.current_token().kind;
             goahead!(self); // skip op
             let start_pos = self.scanner.current_token().position;
-            let right = self.parse_expression()?;
+            // the endpoint stops before another comparison, so that
+            // "> 2 < 1" is not read as "> (2 < 1)"
+            let right = self.parse_add_or_sub()?;
             //let left = Node::new(Var(VarValue::Name("?".to_owned())), start_pos.clone());
             Ok(Node::new(
                 UnaryTest {
@@ -224,7 +308,42 @@ impl Parser<'_> {
     }
 
     fn parse_compare(&mut self) -> NodeResult {
-        self.parse_binop_kinds(&[">", ">=", "<", "<=", "!=", "="], Parser::parse_add_or_sub)
+        let start_pos = self.scanner.current_token().position;
+        let compare_ops = [">", ">=", "<", "<=", "!=", "="];
+        let mut left = self.parse_add_or_sub()?;
+        if self.scanner.expect_kinds(&compare_ops) {
+            let op = self.scanner.current_token().value;
+            goahead!(self);
+            let right = self.parse_add_or_sub()?;
+            left = Node::new(BinOp { op, left, right }, start_pos.clone());
+            // chained comparisons like 1 < 2 < 3 would compare a boolean
+            // against a number, reject them instead
+            if self.scanner.expect_kinds(&compare_ops) {
+                return Err(ParseError::new(format!(
+                    "chained comparison {} {} ... is not supported, combine the comparisons with `and`",
+                    left,
+                    self.scanner.current_token().value,
+                )));
+            }
+        }
+        if self.scanner.expect_keyword("between") {
+            goahead!(self); // skip 'between'
+            let low = self.parse_add_or_sub()?;
+            if !self.scanner.expect_keyword("and") {
+                return Err(self.unexpect_keyword("and"));
+            }
+            goahead!(self); // skip 'and'
+            let high = self.parse_add_or_sub()?;
+            return Ok(Node::new(
+                Between {
+                    value: left,
+                    low,
+                    high,
+                },
+                start_pos,
+            ));
+        }
+        Ok(left)
     }
 
     fn parse_add_or_sub(&mut self) -> NodeResult {
@@ -232,7 +351,41 @@ impl Parser<'_> {
     }
 
     fn parse_mul_or_div(&mut self) -> NodeResult {
-        self.parse_binop_kinds(&["*", "/", "%"], Parser::parse_funccall_or_index_or_dot)
+        let mut start_pos = self.scanner.current_token().position;
+        let mut left = self.parse_power()?;
+        while self.scanner.expect_kinds(&["*", "/", "%"]) || self.expect_mod_op() {
+            // `mod` is the word form of `%`
+            let op = match self.scanner.current_token().value.as_str() {
+                "mod" => "%".to_owned(),
+                v => v.to_owned(),
+            };
+            goahead!(self);
+            let right = self.parse_power()?;
+            left = Node::new(BinOp { op, left, right }, start_pos);
+            start_pos = self.scanner.current_token().position;
+        }
+        Ok(left)
+    }
+
+    // `mod` is a plain name, it is taken as the operator only here,
+    // right after a complete operand, so a variable named `mod` still
+    // resolves
+    fn expect_mod_op(&self) -> bool {
+        self.scanner.expect("name") && self.scanner.current_token().value == "mod"
+    }
+
+    // `**` binds tighter than `*` and is right associative, so
+    // 2 ** 3 ** 2 is 2 ** (3 ** 2)
+    fn parse_power(&mut self) -> NodeResult {
+        let start_pos = self.scanner.current_token().position;
+        let left = self.parse_funccall_or_index_or_dot()?;
+        if !self.scanner.expect("**") {
+            return Ok(left);
+        }
+        let op = self.scanner.current_token().value;
+        goahead!(self); // skip '**'
+        let right = self.parse_power()?;
+        Ok(Node::new(BinOp { op, left, right }, start_pos))
     }
 
     fn parse_funccall_or_index_or_dot(&mut self) -> NodeResult {
@@ -282,9 +435,8 @@ impl Parser<'_> {
     fn parse_funcall_arg(&mut self) -> Result<FuncCallArg, ParseError> {
         let arg = self.parse_expression()?;
         if self.scanner.expect(":") {
-            goahead!(self);
+            goahead!(self); // skip ":"
             if let Var(v) = *arg.syntax {
-                goahead!(self); // skip ":"
                 let arg_value = self.parse_expression()?;
                 return Ok(FuncCallArg {
                     arg_name: v.value(),
@@ -322,7 +474,20 @@ impl Parser<'_> {
     fn parse_dot_rest(&mut self, left: Box<Node>) -> NodeResult {
         goahead!(self); // skip "."
         let start_pos = left.clone().start_pos;
-        let attr = self.parse_name(None)?;
+        let attr = match self.scanner.current_token().kind {
+            "backtick" => self.parse_var_name(None)?,
+            // nothing but a key can follow the dot, so a keyword like
+            // `for` or `in` is taken as the key
+            "keyword" => match self.parse_name(None) {
+                Ok(name) => name,
+                Err(_) => {
+                    let token = self.scanner.current_token();
+                    goahead!(self);
+                    token.value
+                }
+            },
+            _ => self.parse_name(None)?,
+        };
         return Ok(Node::new(DotOp { left, attr }, start_pos));
     }
 
@@ -354,8 +519,24 @@ impl Parser<'_> {
         }
     }
 
+    // whether the text starts a known name with ops or keywords
+    fn is_compound_name_prefix(&mut self, text: &str) -> bool {
+        if self.engine.is_prelude_name_prefix(text) {
+            return true;
+        }
+        if self.name_prefixes.is_none() {
+            self.name_prefixes = Some(self.engine.compound_name_prefixes());
+        }
+        self.name_prefixes.as_ref().unwrap().contains(text)
+    }
+
     fn parse_name(&mut self, stop_keywords: Option<&[&str]>) -> Result<String, ParseError> {
         let mut token_stack: Vec<Token> = Vec::new();
+        // name_buffer[..name_ends[i]] is the name of token_stack[..=i]
+        let mut name_buffer = String::new();
+        let mut name_ends: Vec<usize> = Vec::new();
+        // index of the first op or keyword token
+        let mut first_special: Option<usize> = None;
 
         while self
             .scanner
@@ -368,27 +549,43 @@ impl Parser<'_> {
                     break;
                 }
             }
-            token_stack.push(token);
-            goahead!(self);
-        }
-        while token_stack.len() > 0 {
-            let mut name_buffer = String::new();
-            let mut found_op = false;
-            for (i, t) in token_stack.iter().enumerate() {
-                if t.kind != "keyword" && t.kind != "name" {
-                    found_op = true;
-                }
-                if i > 0
-                    && (token_stack[i - 1].position.chars + token_stack[i - 1].value.len()
-                        < t.position.chars)
-                {
+            let prev_len = name_buffer.len();
+            if let Some(prev) = token_stack.last() {
+                if prev.position.chars + prev.value.len() < token.position.chars {
                     name_buffer.push_str(" ");
                 }
-                name_buffer.push_str(t.value.as_str());
             }
-            if !found_op || self.engine.has_name(name_buffer.clone()) {
-                return Ok(name_buffer);
+            name_buffer.push_str(token.value.as_str());
+            // a `mod` after the first token is the operator unless it
+            // makes a known name, e.g. `a mod 3`
+            let is_special =
+                token.kind != "name" || (token.value == "mod" && !token_stack.is_empty());
+            if is_special && first_special.is_none() {
+                first_special = Some(token_stack.len());
+            }
+            // once an op or keyword is met, only a prefix of a known
+            // name can go on, this keeps a long run of tokens like
+            // `a + b + c ...` from being collected for every name
+            if first_special.is_some() && !self.is_compound_name_prefix(&name_buffer) {
+                name_buffer.truncate(prev_len);
+                if first_special == Some(token_stack.len()) {
+                    first_special = None;
+                }
+                break;
+            }
+            token_stack.push(token);
+            name_ends.push(name_buffer.len());
+            goahead!(self);
+        }
+        while let Some(&end) = name_ends.last() {
+            let name = &name_buffer[..end];
+            // a name mixed with ops or keywords, e.g. `a and b`, is
+            // accepted only when it is defined
+            let is_plain = first_special.is_none_or(|i| name_ends.len() <= i);
+            if is_plain || self.engine.has_name(name.to_owned()) {
+                return Ok(name.to_owned());
             }
+            name_ends.pop();
             if let Some(token) = token_stack.pop() {
                 self.scanner.rewind(token);
             }
@@ -402,7 +599,7 @@ impl Parser<'_> {
         if self.scanner.expect("backtick") {
             let t = self.scanner.current_token();
             goahead!(self);
-            return Ok(t.value);
+            return Ok(unquote_backtick(&t.value));
         }
         while self
             .scanner
@@ -447,7 +644,7 @@ impl Parser<'_> {
         let token = self.scanner.current_token();
         goahead!(self);
         Ok(Node::new(
-            Var(VarValue::Backtick(token.value)),
+            Var(VarValue::Backtick(unquote_backtick(&token.value))),
             token.position,
         ))
         //Ok(Node::new(Str(token.value), token.position))
@@ -528,7 +725,8 @@ impl Parser<'_> {
     }
 
     fn parse_map_key(&mut self) -> NodeResult {
-        if self.scanner.expect_kinds(&["name", "backtick"]) {
+        // keywords are valid keys, e.g. {in: 1}
+        if self.scanner.expect_kinds(&["name", "backtick", "keyword"]) {
             let start_pos = self.scanner.current_token().position;
             match self.parse_var_name(None) {
                 Ok(name) => Ok(Node::new(Ident(name), start_pos)),
@@ -730,14 +928,24 @@ impl Parser<'_> {
         let start_pos = self.scanner.current_token().position;
         let cmd = self.scanner.current_token().value;
         goahead!(self); // skip 'some'|'every'
-        let var_name = self.parse_var_name(Some(&["in"]))?;
+        let mut bindings: Vec<IterBinding> = vec![];
+        loop {
+            let var_name = self.parse_var_name(Some(&["in"]))?;
+            if !self.scanner.expect_keyword("in") {
+                return Err(self.unexpect_keyword("in"));
+            }
+            goahead!(self); // skip 'in'
 
-        if !self.scanner.expect_keyword("in") {
-            return Err(self.unexpect_keyword("in"));
+            let list_expr = self.parse_expression()?;
+            bindings.push(IterBinding {
+                var_name,
+                list_expr,
+            });
+            if !self.scanner.expect(",") {
+                break;
+            }
+            goahead!(self); // skip ','
         }
-        goahead!(self); // skip 'in'
-
-        let list_expr = self.parse_expression()?;
         if !self.scanner.expect_keyword("satisfies") {
             return Err(self.unexpect_keyword("satisfies"));
         }
@@ -747,8 +955,7 @@ impl Parser<'_> {
         if cmd == "some".to_owned() {
             Ok(Node::new(
                 SomeExpr {
-                    var_name,
-                    list_expr,
+                    bindings,
                     filter_expr,
                 },
                 start_pos,
@@ -756,8 +963,7 @@ impl Parser<'_> {
         } else {
             Ok(Node::new(
                 EveryExpr {
-                    var_name,
-                    list_expr,
+                    bindings,
                     filter_expr,
                 },
                 start_pos,
@@ -810,6 +1016,15 @@ impl Parser<'_> {
     }
 }
 
+// strip the surrounding backticks of a backtick name token
+fn unquote_backtick(value: &str) -> String {
+    value
+        .strip_prefix('`')
+        .and_then(|v| v.strip_suffix('`'))
+        .unwrap_or(value)
+        .to_owned()
+}
+
 pub fn parse(
     input: &str,
     engine: Box<Engine>,
@@ -818,20 +1033,43 @@ pub fn parse(
     let mut parser = Parser::new(input, engine);
     match parser.parse(top) {
         Ok(n) => Ok(n),
-        Err(err) => Err((err, parser.scanner.current_token().position)),
+        Err(err) => Err((err, parser.scanner.current_position())),
     }
 }
 
+// parse the input as statements separated by ';', collecting all
+// errors instead of stopping at the first one
+pub fn parse_all(
+    input: &str,
+    engine: Box<Engine>,
+) -> (Option<Box<Node>>, Vec<(ParseError, TextPosition)>) {
+    let mut parser = Parser::new(input, engine);
+    parser.parse_all()
+}
+
 #[cfg(test)]
 mod test {
     use crate::eval::Engine;
-    use core::assert_matches::assert_matches;
+    use core::assert_matches;
     #[test]
     fn test_parse_expressions() {
         let testcases = [
             ("a + b(4, 9)", "(+ a (call b [4, 9]))"),
             ("if a > 6 then true else false", "(if (> a 6) true false)"),
             ("{a: 1, \"bbb\": [2, 1]}", r#"{a: 1, "bbb": [2, 1]}"#),
+            ("a between 1 and b + 2", "(between a 1 (+ b 2))"),
+            ("x between 1 and 10 and y", "(and (between x 1 10) y)"),
+            ("a.`in` + a.for", "(+ (. a in) (. a for))"),
+            ("{in: 1, `for`: 2}", "{in: 1, for: 2}"),
+            ("`a b` * 2", "(* `a b` 2)"),
+            (
+                "for `a&b-c` in [2, 3, 4] return `a&b-c` * 2",
+                "(for a&b-c in [2, 3, 4] (* `a&b-c` 2))",
+            ),
+            ("10 mod 3", "(% 10 3)"),
+            ("a mod 3 + 1", "(+ (% a 3) 1)"),
+            ("mod + 1", "(+ mod 1)"),
+            ("mod mod 2", "(% mod 2)"),
             //("> 2, <= 1, a>8", "(unary-tests (> ? 2) (<= ? 1) (> a 8))"),
             //("2>8; 9; true", "(expr-list (> 2 8) 9 true)"),
         ];
@@ -853,6 +1091,12 @@ mod test {
     fn test_parse_unary_tests() {
         let testcases = [
             ("> 2, <= 1, a>8", "(unary-tests (> 2) (<= 1) (> a 8))"),
+            ("? > 5, ? < 10", "(unary-tests (> ? 5) (< ? 10))"),
+            ("> 5, ? < 10, ?", "(unary-tests (> 5) (< ? 10) (= ?))"),
+            ("???, ? + 1 > 3", "(unary-tests (= ???) (> (+ ? 1) 3))"),
+            ("< 5", "(unary-tests (< 5))"),
+            ("[1..5]", "(unary-tests [1..5])"),
+            ("> 2,\n< 1", "(unary-tests (> 2) (< 1))"),
             //("2>8; 9; true", "(expr-list (> 2 8) 9 true)"),
         ];
 
@@ -869,6 +1113,153 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_parse_all() {
+        let engine = Box::new(Engine::new());
+        let (node, errors) = super::parse_all("2 > 8; 9; true", engine);
+        assert!(errors.is_empty());
+        assert_eq!(format!("{}", node.unwrap()), "(expr-list (> 2 8) 9 true)");
+
+        let engine = Box::new(Engine::new());
+        let (node, errors) = super::parse_all("1 +; 2; 3 4; 5", engine);
+        assert!(node.is_none());
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].1.chars, 3);
+        assert_eq!(errors[1].1.chars, 10);
+
+        let engine = Box::new(Engine::new());
+        let (node, errors) = super::parse_all("1 # 2; 3", engine);
+        assert!(node.is_none());
+        assert_eq!(errors.len(), 1);
+        assert_matches!(errors[0].0, super::ParseError::Scan(_));
+    }
+
+    #[test]
+    fn test_parse_unary_tests_without_comma() {
+        for input in ["> 2\n< 1", "1 2", "[1..5]\n\"a\""] {
+            let engine = Box::new(Engine::new());
+            let res = super::parse(input, engine, super::ParseTop::UnaryTests);
+            assert_matches!(
+                res,
+                Err((super::ParseError::Parse(ref x), _)) if x.ends_with("unary tests must be separated by ','"),
+                "{} should not parse",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_chained_comparison() {
+        for input in ["1 < 2 < 3", "a = b = c", "x >= 1 != false"] {
+            let engine = Box::new(Engine::new());
+            let res = super::parse(input, engine, Default::default());
+            assert_matches!(
+                res,
+                Err((super::ParseError::Parse(ref x), _)) if x.starts_with("chained comparison"),
+                "{} should not parse",
+                input
+            );
+        }
+        let engine = Box::new(Engine::new());
+        let res = super::parse("1 < 2 < 3", engine, Default::default());
+        assert_matches!(
+            res,
+            Err((super::ParseError::Parse(ref x), _)) if x == "chained comparison (< 1 2) < ... is not supported, combine the comparisons with `and`"
+        );
+
+        let engine = Box::new(Engine::new());
+        let node = super::parse("(1 < 2) = true", engine, Default::default()).unwrap();
+        assert_eq!(format!("{}", node), "(= (< 1 2) true)");
+    }
+
+    #[test]
+    fn test_parse_long_name_runs() {
+        // the deeply nested result needs a larger stack
+        let handle = std::thread::Builder::new()
+            .stack_size(256 * 1024 * 1024)
+            .spawn(|| {
+                let input = vec!["x"; 2000].join(" + ");
+                let engine = Box::new(Engine::new());
+                let start = std::time::Instant::now();
+                let node = super::parse(&input, engine, Default::default()).unwrap();
+                assert!(format!("{}", node).starts_with("(+ (+ (+ "));
+                start.elapsed()
+            })
+            .unwrap();
+        // collecting the whole run for every name took seconds
+        let elapsed = handle.join().unwrap();
+        assert!(elapsed.as_secs() < 2, "took {:?}", elapsed);
+
+        let input = vec!["word"; 5000].join(" ");
+        let engine = Box::new(Engine::new());
+        let node = super::parse(&input, engine, Default::default()).unwrap();
+        assert_eq!(format!("{}", node), input);
+
+        let engine = Box::new(Engine::new());
+        let node = super::parse("date and time(x) + a-b", engine, Default::default()).unwrap();
+        assert_eq!(format!("{}", node), "(- (+ (call date and time [x]) a) b)");
+    }
+
+    #[test]
+    fn test_compound_name_prefixes() {
+        // the prelude prefixes are built once and shared by the parses
+        let prelude = &crate::prelude::PRELUDE;
+        assert!(std::ptr::eq(
+            prelude.name_prefixes(),
+            prelude.name_prefixes()
+        ));
+        assert!(prelude.name_prefixes().contains("date and"));
+
+        // the scope prefixes only hold the variables
+        let mut engine = Box::new(Engine::new());
+        engine.set_var("net - tax".to_owned(), crate::values::value::Value::NullV);
+        let prefixes = engine.compound_name_prefixes();
+        assert!(prefixes.contains("net -"));
+        assert!(!prefixes.contains("date and"));
+        let node =
+            super::parse("net - tax and date and time(x)", engine, Default::default()).unwrap();
+        assert_eq!(
+            format!("{}", node),
+            "(and net - tax (call date and time [x]))"
+        );
+    }
+
+    #[test]
+    fn test_parse_quantified_bindings() {
+        let engine = Box::new(Engine::new());
+        let node = super::parse("some a in b satisfies a > 1", engine, Default::default()).unwrap();
+        assert_eq!(format!("{}", node), "(some a in b satisfies (> a 1))");
+
+        let engine = Box::new(Engine::new());
+        let node = super::parse(
+            "every x in [1, 2], y in [3, 4] satisfies x < y",
+            engine,
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", node),
+            "(every x in [1, 2], y in [3, 4] satisfies (< x y))"
+        );
+    }
+
+    #[test]
+    fn test_parse_power() {
+        let testcases = [
+            ("2 ** 3", "(** 2 3)"),
+            ("2 ** 3 ** 2", "(** 2 (** 3 2))"),
+            ("2 * 3 ** 2", "(* 2 (** 3 2))"),
+            ("2 ** 3 * 2", "(* (** 2 3) 2)"),
+            ("a.b ** f(2)", "(** (. a b) (call f [2]))"),
+            ("2*3", "(* 2 3)"),
+        ];
+        for (input, output) in testcases {
+            let engine = Box::new(Engine::new());
+            let node = super::parse(input, engine, Default::default()).unwrap();
+            assert_eq!(format!("{}", node), output, "input: {}", input);
+        }
+    }
+
     #[test]
     fn test_parse_func_def() {
         let input = "function(a, b) a + b   ";
@@ -892,6 +1283,18 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_parse_func_def_multibyte() {
+        let input = r#"function(a) a + "café 名前" "#;
+        let engine = Box::new(Engine::new());
+        let node = super::parse(input, engine, Default::default()).unwrap();
+        if let crate::ast::NodeSyntax::FuncDef { code: c, .. } = *node.syntax {
+            assert_eq!(c.as_str(), input);
+        } else {
+            panic!("function definition expected");
+        }
+    }
+
     #[test]
     fn test_parse_dup_arg_name() {
         let engine = Box::new(Engine::new());
@@ -4,17 +4,20 @@ use rand::prelude::*;
 use std::borrow::Borrow;
 use std::cell::RefCell;
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
+use std::sync::OnceLock;
 
-use super::eval::{EvalError, EvalResult};
-use super::values::context::Context;
+use super::ast::Node;
+use super::eval::{Dialect, Engine, EvalError, EvalResult};
+use super::helpers::{ascii_fold, slugify, title_case};
+use super::values::context::{Context, ContextRef};
 use super::values::func::{MacroBody, MacroT, NativeFunc, NativeFuncBody};
 use super::values::numeric::Numeric;
 use super::values::range::install_range_prelude;
-use super::values::temporal::install_temporal_prelude;
+use super::values::temporal::{aggregate_durations, install_temporal_prelude};
 use super::values::value::Value::{self, *};
-use super::values::value::ValueError;
+use super::values::value::{ValueError, ValueType};
 
 fn from_feel_index(idx: usize) -> usize {
     idx - 1
@@ -32,19 +35,439 @@ pub fn range_check(pos: usize, low: usize, high: usize) -> Result<usize, EvalErr
     }
 }
 
+// the number argument of log functions must be positive
+fn expect_log_number(arg: &Value) -> Result<Numeric, EvalError> {
+    let n = arg.expect_number("argument[1] `number`")?;
+    if n <= Numeric::ZERO {
+        return Err(EvalError::value_error(
+            format!(
+                "argument[1] `number`, expect positive number, but {} found",
+                n
+            )
+            .as_str(),
+        ));
+    }
+    Ok(n)
+}
+
+// the logarithm of the number argument, to the base when given. a
+// number out of the f64 range, e.g. 10 ** 400, is a ValueError
+fn log_of(n: &Numeric, base: Option<&Numeric>) -> EvalResult {
+    let ln = n.ln().ok_or(EvalError::value_error(
+        "argument[1] `number`, the logarithm is not a finite number",
+    ))?;
+    match base {
+        Some(base) => n
+            .log(base)
+            .map(Value::NumberV)
+            .ok_or(EvalError::value_error(
+                "argument[2] `base`, expect positive number other than 1",
+            )),
+        None => Ok(Value::NumberV(ln)),
+    }
+}
+
+// nesting cap of a full flatten, a deeper list is likely a list
+// containing itself
+const MAX_FLATTEN_DEPTH: usize = 64;
+
+// total length cap of a run length decoded list, a larger count is
+// likely a mistake and would exhaust the memory
+const MAX_DECODED_LEN: usize = 1_000_000;
+
+fn flatten_into(
+    arr: &[Value],
+    levels: usize,
+    full: bool,
+    res: &mut Vec<Value>,
+) -> Result<(), EvalError> {
+    for v in arr.iter() {
+        match v {
+            Value::ArrayV(a) if levels > 0 => {
+                flatten_into(&a.as_ref().borrow(), levels - 1, full, res)?;
+            }
+            Value::ArrayV(_) if full => {
+                return Err(EvalError::value_error(
+                    format!("flatten exceeds the max depth {}", MAX_FLATTEN_DEPTH).as_str(),
+                ));
+            }
+            x => res.push(x.clone()),
+        }
+    }
+    Ok(())
+}
+
+// values in iteration order with the later duplicates removed, a hash
+// set is used when all the values are scalars, otherwise the values are
+// compared one by one. Value has interior mutability in arrays and
+// contexts, but only scalars are put into the hash set, their hashes
+// cannot change
+#[allow(clippy::mutable_key_type)]
+fn distinct_values<'a>(values: impl Iterator<Item = &'a Value> + Clone) -> Vec<Value> {
+    let mut res: Vec<Value> = vec![];
+    if values.clone().all(|v| v.is_hashable_scalar()) {
+        let mut seen: HashSet<&Value> = HashSet::new();
+        for v in values {
+            if seen.insert(v) {
+                res.push(v.clone());
+            }
+        }
+    } else {
+        for v in values {
+            if !res.contains(v) {
+                res.push(v.clone());
+            }
+        }
+    }
+    res
+}
+
+// the string, match and replacement arguments of the literal
+// replacements, an empty match is rejected
+fn replace_args(args: &HashMap<String, Value>) -> Result<(String, String, String), EvalError> {
+    let s = args
+        .get(&"string".to_owned())
+        .unwrap()
+        .expect_string("argument[1] `string`")?;
+    let m = args
+        .get(&"match".to_owned())
+        .unwrap()
+        .expect_string("argument[2] `match`")?;
+    if m.is_empty() {
+        return Err(EvalError::value_error("argument[2] `match` is empty"));
+    }
+    let r = args
+        .get(&"replacement".to_owned())
+        .unwrap()
+        .expect_string("argument[3] `replacement`")?;
+    Ok((s, m, r))
+}
+
+// put the value at the path of the context in place, the nesting
+// depth is checked before the context is changed. the result is no
+// deeper than the context itself or the value wrapped by the
+// contexts along the path
+fn put_path(eng: &Engine, m: &ContextRef, path: &[String], value: &Value) -> EvalResult {
+    let mut wrapped = value.clone();
+    for key in path.iter().rev() {
+        let mut ctx = Context::new();
+        ctx.insert(key.clone(), wrapped);
+        wrapped = Value::ContextV(Rc::new(RefCell::new(ctx)));
+    }
+    eng.check_nesting_depth(&wrapped)?;
+    eng.check_nesting_depth(&Value::ContextV(m.clone()))?;
+    m.as_ref().borrow_mut().insert_path(path, value.clone());
+    Ok(Value::ContextV(m.clone()))
+}
+
+// split a dotted path into keys, `\.` is a dot inside a key and `\\`
+// a backslash, empty keys are rejected
+fn dotted_path(input: &str) -> Result<Vec<String>, EvalError> {
+    let mut keys: Vec<String> = vec![];
+    let mut key = String::new();
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(e @ ('.' | '\\')) => key.push(e),
+                _ => {
+                    return Err(EvalError::value_error(
+                        format!("bad escape in path \"{}\"", input).as_str(),
+                    ))
+                }
+            },
+            '.' => keys.push(std::mem::take(&mut key)),
+            c => key.push(c),
+        }
+    }
+    keys.push(key);
+    if keys.iter().any(|k| k.is_empty()) {
+        return Err(EvalError::value_error(
+            format!("empty key in path \"{}\"", input).as_str(),
+        ));
+    }
+    Ok(keys)
+}
+
+// the elements to aggregate, either given as arguments, e.g. sum(1, 2),
+// or as a single list argument, e.g. sum([1, 2])
+fn aggregate_list(args: &HashMap<String, Value>) -> Result<Vec<Value>, EvalError> {
+    let arg0 = args.get(&"list".to_owned()).unwrap();
+    let arr = arg0.expect_array("arguments `list`")?;
+    if arr.len() == 1 {
+        if let Value::ArrayV(inner) = &arr[0] {
+            let inner_arr: &RefCell<Vec<Value>> = inner.borrow();
+            return Ok(inner_arr.borrow().clone());
+        }
+    }
+    Ok(arr.clone())
+}
+
+// the numbers to aggregate, non-number elements are skipped, or
+// rejected with a TypeError in strict mode
+fn aggregate_numbers(arr: &[Value], strict: bool) -> Result<Vec<Numeric>, EvalError> {
+    let mut numbers: Vec<Numeric> = vec![];
+    for (i, v) in arr.iter().enumerate() {
+        match v {
+            Value::NumberV(n) => numbers.push(n.clone()),
+            _ if strict => {
+                return Err(EvalError::type_error(
+                    format!(
+                        "number at element[{}], but {} found",
+                        to_feel_index(i),
+                        v.data_type()
+                    )
+                    .as_str(),
+                ))
+            }
+            _ => (),
+        }
+    }
+    Ok(numbers)
+}
+
+// the least or greatest element, elements without an order such as
+// booleans are skipped. in strict mode all elements must be of the
+// type of the first one
+fn aggregate_min_max(arr: &[Value], ordering: cmp::Ordering, strict: bool) -> EvalResult {
+    let mut found: Option<Value> = None;
+
+    for (i, v) in arr.iter().enumerate() {
+        if let Some(ref current) = found {
+            if strict && v.data_type() != current.data_type() {
+                return Err(EvalError::type_error(
+                    format!(
+                        "{} at element[{}], but {} found",
+                        current.data_type(),
+                        to_feel_index(i),
+                        v.data_type()
+                    )
+                    .as_str(),
+                ));
+            }
+            match v.partial_cmp(current) {
+                Some(ord) if ord == ordering => found = Some(v.clone()),
+                Some(_) => (),
+                // ordered values which cannot be compared, e.g. a
+                // number and a date, are an error even when not strict
+                None if v.is_range_endpoint() => {
+                    return Err(EvalError::type_error(
+                        format!(
+                            "values comparable with {} at element[{}], but {} found",
+                            current.data_type(),
+                            to_feel_index(i),
+                            v.data_type()
+                        )
+                        .as_str(),
+                    ))
+                }
+                None => (),
+            }
+        } else if strict || v.is_range_endpoint() {
+            found = Some(v.clone());
+        }
+    }
+    Ok(found.unwrap_or(Value::NullV))
+}
+
+// a list starting with a duration is aggregated as durations, the
+// other elements are skipped, or rejected in strict mode
+fn aggregate_duration_list(arr: &[Value], strict: bool, mean: bool) -> EvalResult {
+    let mut durations: Vec<(iso8601::Duration, bool)> = vec![];
+    for (i, v) in arr.iter().enumerate() {
+        match v {
+            Value::DurationV { duration, negative } => durations.push((*duration, *negative)),
+            _ if strict => {
+                return Err(EvalError::type_error(
+                    format!(
+                        "duration at element[{}], but {} found",
+                        to_feel_index(i),
+                        v.data_type()
+                    )
+                    .as_str(),
+                ))
+            }
+            _ => (),
+        }
+    }
+    Ok(aggregate_durations(&durations, mean)?)
+}
+
+// all() and any() of DMN 1.2, the element equal to `decisive`
+// decides the result, otherwise a non-boolean element makes it null
+fn all_or_any(arr: &[Value], decisive: bool) -> Value {
+    if arr.contains(&BoolV(decisive)) {
+        BoolV(decisive)
+    } else if arr.iter().all(|v| matches!(v, BoolV(_))) {
+        BoolV(!decisive)
+    } else {
+        Value::NullV
+    }
+}
+
+// the sum, 0 for an empty list or null following DMN 1.2
+fn aggregate_sum(eng: &Engine, arr: &[Value], strict: bool) -> EvalResult {
+    if arr.is_empty() && eng.dialect() == Dialect::Dmn12 {
+        return Ok(Value::NullV);
+    }
+    if let Some(Value::DurationV { .. }) = arr.first() {
+        return aggregate_duration_list(arr, strict, false);
+    }
+    let mut sum = Numeric::ZERO;
+    for n in aggregate_numbers(arr, strict)? {
+        sum += n;
+    }
+    Ok(Value::NumberV(sum))
+}
+
+fn aggregate_mean(arr: &[Value], strict: bool) -> EvalResult {
+    if let Some(Value::DurationV { .. }) = arr.first() {
+        return aggregate_duration_list(arr, strict, true);
+    }
+    let numbers = aggregate_numbers(arr, strict)?;
+    if numbers.is_empty() {
+        return Ok(Value::NullV);
+    }
+    let count = Numeric::from_usize(numbers.len());
+    let mut sum = Numeric::ZERO;
+    for n in numbers {
+        sum += n;
+    }
+    Ok(Value::NumberV(sum / count))
+}
+
+// the optional `sample` flag of stddev, false by default
+fn stddev_sample(args: &HashMap<String, Value>) -> Result<bool, EvalError> {
+    match args.get(&"sample".to_owned()) {
+        None | Some(Value::NullV) => Ok(false),
+        Some(Value::BoolV(b)) => Ok(*b),
+        Some(v) => Err(EvalError::type_error(
+            format!("argument `sample` to be boolean, found {}", v.data_type()).as_str(),
+        )),
+    }
+}
+
+// the values of stddev, either a single list or the numbers as the
+// positional arguments. a list followed by other positional values is
+// rejected, e.g. stddev([1, 2, 3], true) where `sample` is not named
+fn stddev_list(args: &HashMap<String, Value>) -> Result<Vec<Value>, EvalError> {
+    let arg0 = args.get(&"list".to_owned()).unwrap();
+    let arr = arg0.expect_array("arguments `list`")?;
+    if arr.len() > 1 && matches!(arr[0], Value::ArrayV(_)) {
+        return Err(EvalError::value_error(
+            "argument `list`, a list cannot be mixed with other positional values, pass `sample` by name",
+        ));
+    }
+    aggregate_list(args)
+}
+
+// the population standard deviation, or the sample one dividing the
+// squared deviations by count - 1
+fn aggregate_stddev(arr: &[Value], strict: bool, sample: bool) -> EvalResult {
+    let numbers = aggregate_numbers(arr, strict)?;
+    if sample && numbers.len() < 2 {
+        return Err(EvalError::value_error(
+            format!(
+                "sample stddev needs at least 2 numbers, found {}",
+                numbers.len()
+            )
+            .as_str(),
+        ));
+    }
+    if numbers.is_empty() {
+        return Ok(Value::NullV);
+    }
+    let count = Numeric::from_usize(numbers.len());
+    let mut sum = Numeric::ZERO;
+    for n in numbers.iter() {
+        sum += n.clone();
+    }
+    let avg = sum / count.clone();
+
+    let mut dev = Numeric::ZERO;
+    for n in numbers {
+        let diff = n - avg.clone();
+        dev += diff.clone() * diff;
+    }
+    dev = if sample {
+        dev / (count - Numeric::ONE)
+    } else {
+        dev / count
+    };
+    dev.sqrt().map_or(Ok(NullV), |n| Ok(NumberV(n)))
+}
+
+// evaluate the projection expression for each element of the list,
+// the entries of a context element are visible as variables and the
+// element itself is `item`, e.g. sum over(orders, price * amount)
+fn project_list(
+    eng: &mut Engine,
+    nodes: &HashMap<String, Box<Node>>,
+) -> Result<Vec<Value>, EvalError> {
+    let list_node = nodes.get(&"list".to_owned()).unwrap();
+    let projection = nodes.get(&"projection".to_owned()).unwrap();
+    let list_value = eng.eval(list_node.clone())?;
+    let arr = list_value.expect_array("argument[1] `list`")?.clone();
+
+    let mut res: Vec<Value> = vec![];
+    for item in arr.into_iter() {
+        eng.push_frame();
+        if let Value::ContextV(ref ctx) = item {
+            let entries = ctx.as_ref().borrow().entries();
+            for (k, v) in entries {
+                eng.bind_var(k, v);
+            }
+        }
+        eng.bind_var("item".to_owned(), item);
+        let v = eng.eval(projection.clone());
+        eng.pop_frame();
+        res.push(v?);
+    }
+    Ok(res)
+}
+
 #[derive(Clone)]
 pub struct Prelude {
     vars: HashMap<String, Value>,
+    // prefixes of the compound names, built on first use and dropped
+    // when the names change
+    name_prefixes: OnceLock<HashSet<String>>,
+}
+
+/// add the prefixes of a name made of several words or containing
+/// ops, e.g. "date", "date and" and "date and time" of "date and time"
+pub fn add_name_prefixes(prefixes: &mut HashSet<String>, name: &str) {
+    if !name.contains(|c: char| c.is_whitespace() || "+-*/".contains(c)) {
+        return;
+    }
+    for (i, _) in name.char_indices().skip(1) {
+        prefixes.insert(name[..i].to_owned());
+    }
+    prefixes.insert(name.to_owned());
 }
 
 impl Prelude {
     pub fn new() -> Prelude {
         Prelude {
             vars: HashMap::new(),
+            name_prefixes: OnceLock::new(),
         }
     }
 
+    /// the prefixes of the compound built-in names, see
+    /// add_name_prefixes(), computed once
+    pub fn name_prefixes(&self) -> &HashSet<String> {
+        self.name_prefixes.get_or_init(|| {
+            let mut prefixes = HashSet::new();
+            for name in self.vars.keys() {
+                add_name_prefixes(&mut prefixes, name);
+            }
+            prefixes
+        })
+    }
+
     pub fn set_var(&mut self, name: String, value: Value) {
+        self.name_prefixes = OnceLock::new();
         self.vars.insert(name, value);
     }
 
@@ -55,6 +478,17 @@ impl Prelude {
         }
     }
 
+    /// drop a built-in, e.g. from a clone of PRELUDE given to
+    /// Engine::with_prelude()
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        self.name_prefixes = OnceLock::new();
+        self.vars.remove(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.vars.keys()
+    }
+
     pub fn has_name(&self, name: String) -> bool {
         match self.vars.get(&name) {
             Some(_v) => true,
@@ -86,6 +520,7 @@ impl Prelude {
         let func_t = NativeFunc {
             name: name.to_owned(),
             body: func,
+            named_args: Vec::new(),
         };
         let func_value = NativeFuncV {
             func: func_t,
@@ -107,6 +542,7 @@ impl Prelude {
         let func_t = NativeFunc {
             name: name.to_owned(),
             body: func,
+            named_args: Vec::new(),
         };
         let func_value = NativeFuncV {
             func: func_t,
@@ -123,28 +559,82 @@ impl Prelude {
         self.set_var(name.to_owned(), func_value);
     }
 
+    // a var arg function taking optional args by name only, as the
+    // positional args all go to the var arg
+    pub fn add_native_func_with_named_args(
+        &mut self,
+        name: &str,
+        named_args: &[&str],
+        var_arg: &str,
+        func: NativeFuncBody,
+    ) {
+        let func_t = NativeFunc {
+            name: name.to_owned(),
+            body: func,
+            named_args: named_args.iter().map(|&s| String::from(s)).collect(),
+        };
+        let func_value = NativeFuncV {
+            func: func_t,
+            required_args: Vec::new(),
+            optional_args: Vec::new(),
+            var_arg: Some(var_arg.to_owned()),
+        };
+        self.set_var(name.to_owned(), func_value);
+    }
+
     pub fn load_preludes(&mut self) {
-        // self.add_native_func("set", &["name", "value"], |eng, args| -> EvalResult {
-        //     let name_node = args.get(&"name".to_owned()).unwrap();
-        //     let var_name = match name_node {
-        //         StrV(value) => value.clone(),
-        //         _ => return Err(EvalError::runtime("argument name should be string")),
-        //     };
-        //     let value = args.get(&"value".to_owned()).unwrap();
-        //     eng.set_var(var_name, value.clone());
-        //     Ok(value.clone())
-        // });
-
-        // self.add_native_func("bind", &["name", "value"], |eng, args| -> EvalResult {
-        //     let name_node = args.get(&"name".to_owned()).unwrap();
-        //     let var_name = match name_node {
-        //         StrV(value) => value.clone(),
-        //         _ => return Err(EvalError::runtime("argument name should be string")),
-        //     };
-        //     let value = args.get(&"value".to_owned()).unwrap();
-        //     eng.bind_var(var_name, value.clone());
-        //     Ok(value.clone())
-        // });
+        // set the variable where it is defined, or bind it to the
+        // innermost frame when not defined yet. the value is coerced
+        // when the variable was set by `set typed`
+        self.add_native_func("set", &["name", "value"], |eng, args| -> EvalResult {
+            let name_node = args.get(&"name".to_owned()).unwrap();
+            let var_name = match name_node {
+                StrV(value) => value.clone(),
+                _ => return Err(EvalError::runtime("argument name should be string")),
+            };
+            let value = args.get(&"value".to_owned()).unwrap();
+            if let Some(value_type) = eng.var_type(&var_name) {
+                return eng.set_var_typed(var_name, value.clone(), value_type);
+            }
+            eng.set_var(var_name, value.clone());
+            Ok(value.clone())
+        });
+
+        // set the variable and record its type, the value and the
+        // values set later are coerced to the type
+        self.add_native_func(
+            "set typed",
+            &["name", "value", "type"],
+            |eng, args| -> EvalResult {
+                let name = args
+                    .get(&"name".to_owned())
+                    .unwrap()
+                    .expect_string("argument[1] `name`")?;
+                let value = args.get(&"value".to_owned()).unwrap();
+                let type_name = args
+                    .get(&"type".to_owned())
+                    .unwrap()
+                    .expect_string("argument[3] `type`")?;
+                let Some(value_type) = ValueType::from_name(type_name.as_str()) else {
+                    return Err(EvalError::value_error(
+                        format!("unknown type {}", type_name).as_str(),
+                    ));
+                };
+                eng.set_var_typed(name, value.clone(), value_type)
+            },
+        );
+
+        // bind the variable to the innermost frame
+        self.add_native_func("bind", &["name", "value"], |eng, args| -> EvalResult {
+            let name_node = args.get(&"name".to_owned()).unwrap();
+            let var_name = match name_node {
+                StrV(value) => value.clone(),
+                _ => return Err(EvalError::runtime("argument name should be string")),
+            };
+            let value = args.get(&"value".to_owned()).unwrap();
+            eng.bind_var(var_name, value.clone());
+            Ok(value.clone())
+        });
 
         // conversion functions
         // refer to https://docs.camunda.io/docs/components/modeler/feel/builtin-functions/feel-built-in-functions-conversion/
@@ -153,18 +643,105 @@ impl Prelude {
             Ok(Value::StrV(v.to_string()))
         });
 
+        // convert the value to the named type, e.g. coerce("5", "number"),
+        // null is returned when the value cannot be converted
+        self.add_native_func("coerce", &["value", "type"], |_, args| -> EvalResult {
+            let v = args.get(&"value".to_owned()).unwrap();
+            let type_name = args
+                .get(&"type".to_owned())
+                .unwrap()
+                .expect_string("argument[2] `type`")?;
+            let Some(value_type) = ValueType::from_name(type_name.as_str()) else {
+                return Err(EvalError::value_error(
+                    format!("unknown type {}", type_name).as_str(),
+                ));
+            };
+            Ok(v.coerce_to(value_type).unwrap_or(Value::NullV))
+        });
+
         self.add_native_func("number", &["from"], |_, args| -> EvalResult {
             let v = args.get(&"from".to_owned()).unwrap();
             let n = v.parse_number()?;
             Ok(Value::NumberV(n))
         });
 
+        // strict sibling of number(), the error carries the input text
+        self.add_native_func("to number", &["from"], |_, args| -> EvalResult {
+            let v = args.get(&"from".to_owned()).unwrap();
+            let s = v.expect_string("argument[1] `from`")?;
+            match Numeric::from_str(s.as_str()) {
+                Some(n) => Ok(Value::NumberV(n)),
+                None => Err(EvalError::value_error(
+                    format!("fail to parse number from \"{}\"", s).as_str(),
+                )),
+            }
+        });
+
+        // parse a JSON string, objects become contexts
+        self.add_native_func("parse json", &["string"], |_, args| -> EvalResult {
+            let v = args.get(&"string".to_owned()).unwrap();
+            let s = v.expect_string("argument[1] `string`")?;
+            Ok(Value::from_json_str(s.as_str())?)
+        });
+
+        // serialize the value as compact JSON, or indented JSON when
+        // pretty is true, see Value::write_json() for the encoding
+        self.add_native_func_with_optional_args(
+            "to json string",
+            &["value"],
+            &["pretty"],
+            None,
+            |_, args| -> EvalResult {
+                let v = args.get(&"value".to_owned()).unwrap();
+                let pretty = match args.get(&"pretty".to_owned()) {
+                    None | Some(Value::NullV) => false,
+                    Some(Value::BoolV(b)) => *b,
+                    Some(p) => {
+                        return Err(EvalError::type_error(
+                            format!(
+                                "argument[2] `pretty` to be boolean, found {}",
+                                p.data_type()
+                            )
+                            .as_str(),
+                        ))
+                    }
+                };
+                Ok(Value::StrV(v.to_json_string(pretty)?))
+            },
+        );
+
+        // hash value of a value as a hex string, functions are not
+        // hashable, ranges are hashed by their endpoints
+        self.add_native_func("hash value", &["value"], |_, args| -> EvalResult {
+            let v = args.get(&"value".to_owned()).unwrap();
+            if v.is_function() {
+                return Err(EvalError::type_error(
+                    format!("argument[1] `value`, {} is not hashable", v.data_type()).as_str(),
+                ));
+            }
+            Ok(Value::StrV(format!("{:016x}", v.stable_hash())))
+        });
+
         // boolean functions
         // refer to https://docs.camunda.io/docs/components/modeler/feel/builtin-functions/feel-built-in-functions-boolean/
         self.add_native_func("not", &["from"], |_, args| -> EvalResult {
             let v = args.get(&"from".to_owned()).unwrap();
             Ok(Value::BoolV(!v.bool_value()))
         });
+        // explicit truthiness, under the loose dialect null, false, zero
+        // and empty strings, lists or contexts are false and the other
+        // values are true, the DMN 1.2 dialect only takes booleans and
+        // null
+        self.add_native_func("to boolean", &["from"], |eng, args| -> EvalResult {
+            let v = args.get(&"from".to_owned()).unwrap();
+            match (eng.dialect(), v) {
+                (Dialect::Loose, _) => Ok(Value::BoolV(v.bool_value())),
+                (Dialect::Dmn12, BoolV(_) | NullV) => Ok(v.clone()),
+                (Dialect::Dmn12, _) => Err(EvalError::type_error(
+                    format!("argument[1] `from`, boolean, but {} found", v.data_type()).as_str(),
+                )),
+            }
+        });
         self.add_macro("is defined", &["value"], |eng, nodes| -> EvalResult {
             let value_node = nodes.get(&"value".to_owned()).unwrap();
             eng.is_defined(value_node)
@@ -187,10 +764,18 @@ impl Prelude {
         self.add_native_func("string length", &["string"], |_, args| -> EvalResult {
             let v = args.get(&"string".to_owned()).unwrap();
             let s = v.expect_string("argument[1]")?;
-            let lenn = Numeric::from_usize(s.len());
+            // count unicode scalar values instead of bytes
+            let lenn = Numeric::from_usize(s.chars().count());
             Ok(Value::NumberV(lenn))
         });
 
+        self.add_native_func("code points", &["string"], |_, args| -> EvalResult {
+            let v = args.get(&"string".to_owned()).unwrap();
+            let s = v.expect_string("argument[1] `string`")?;
+            let res: Vec<Value> = s.chars().map(|c| Value::StrV(c.to_string())).collect();
+            Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+        });
+
         self.add_native_func_with_optional_args(
             "substring",
             &["string", "start position"],
@@ -200,18 +785,28 @@ impl Prelude {
                 let v = args.get(&"string".to_owned()).unwrap();
                 let s = v.expect_string("argument[1] `string`")?;
                 let start_v = args.get(&"start position".to_owned()).unwrap();
-                let start_position = start_v.expect_usize("argument[2] `start position`")?;
-                if start_position < 1 || start_position > s.len() {
+                let start_position = start_v.expect_integer("argument[2] `start position`")?;
+                // positions are counted by chars, a negative start
+                // position counts from the end
+                let chars: Vec<char> = s.chars().collect();
+                let start = if start_position < 0 {
+                    chars.len() as isize + start_position
+                } else {
+                    start_position - 1
+                };
+                if start < 0 || start >= chars.len() as isize {
                     return Ok(Value::StrV("".to_owned()));
                 }
-                // 'length' is the optional value
-                let substr = if let Some(lenv) = args.get(&"length".to_owned()) {
-                    let len = lenv.expect_usize("argument[3] `length`")?;
-                    &s.as_str()[(start_position - 1)..(cmp::min(start_position - 1 + len, s.len()))]
+                let start = start as usize;
+                // 'length' is the optional value, clamped to the rest
+                // of the string
+                let end = if let Some(lenv) = args.get(&"length".to_owned()) {
+                    let len = lenv.expect_integer("argument[3] `length`")?;
+                    start + cmp::min(cmp::max(len, 0) as usize, chars.len() - start)
                 } else {
-                    &s.as_str()[(start_position - 1)..]
+                    chars.len()
                 };
-                Ok(Value::StrV(substr.to_owned()))
+                Ok(Value::StrV(chars[start..end].iter().collect()))
             },
         );
 
@@ -254,6 +849,8 @@ impl Prelude {
             },
         );
 
+        // casing follows the Unicode default mappings and does not
+        // depend on the locale, e.g. "i" is always "I"
         self.add_native_func("upper case", &["string"], |_, args| -> EvalResult {
             let v = args.get(&"string".to_owned()).unwrap();
             let s = v.expect_string("argument[1] `string`")?;
@@ -266,6 +863,43 @@ impl Prelude {
             Ok(Value::StrV(s.to_lowercase()))
         });
 
+        self.add_native_func("title case", &["string"], |_, args| -> EvalResult {
+            let v = args.get(&"string".to_owned()).unwrap();
+            let s = v.expect_string("argument[1] `string`")?;
+            Ok(Value::StrV(title_case(s.as_str())))
+        });
+
+        self.add_native_func("ascii fold", &["string"], |_, args| -> EvalResult {
+            let v = args.get(&"string".to_owned()).unwrap();
+            let s = v.expect_string("argument[1] `string`")?;
+            Ok(Value::StrV(ascii_fold(s.as_str())))
+        });
+
+        self.add_native_func("slugify", &["string"], |_, args| -> EvalResult {
+            let v = args.get(&"string".to_owned()).unwrap();
+            let s = v.expect_string("argument[1] `string`")?;
+            Ok(Value::StrV(slugify(s.as_str())))
+        });
+
+        // plain text replacement, the match is not a regex pattern
+        self.add_native_func(
+            "replace literal",
+            &["string", "match", "replacement"],
+            |_, args| -> EvalResult {
+                let (s, m, r) = replace_args(&args)?;
+                Ok(Value::StrV(s.replace(m.as_str(), r.as_str())))
+            },
+        );
+
+        self.add_native_func(
+            "replace first",
+            &["string", "match", "replacement"],
+            |_, args| -> EvalResult {
+                let (s, m, r) = replace_args(&args)?;
+                Ok(Value::StrV(s.replacen(m.as_str(), r.as_str(), 1)))
+            },
+        );
+
         self.add_native_func("contains", &["string", "match"], |_, args| -> EvalResult {
             let v = args.get(&"string".to_owned()).unwrap();
             let s = v.expect_string("argument[1] `string`")?;
@@ -274,22 +908,68 @@ impl Prelude {
             Ok(Value::BoolV(s.contains(match_s.as_str())))
         });
 
+        // the 1-based character index of the first occurrence of match,
+        // or null when not found
         self.add_native_func(
-            "starts with",
+            "index of string",
+            &["string", "match"],
+            |_, args| -> EvalResult {
+                let v = args.get(&"string".to_owned()).unwrap();
+                let s = v.expect_string("argument[1] `string`")?;
+                let mv = args.get(&"match".to_owned()).unwrap();
+                let match_s = mv.expect_string("argument[2] `match`")?;
+                match s.find(match_s.as_str()) {
+                    Some(pos) => Ok(Value::from_usize(to_feel_index(s[..pos].chars().count()))),
+                    None => Ok(Value::NullV),
+                }
+            },
+        );
+
+        // the 1-based character index of the last occurrence of match,
+        // or null when not found
+        self.add_native_func(
+            "last index of string",
             &["string", "match"],
             |_, args| -> EvalResult {
                 let v = args.get(&"string".to_owned()).unwrap();
                 let s = v.expect_string("argument[1] `string`")?;
                 let mv = args.get(&"match".to_owned()).unwrap();
                 let match_s = mv.expect_string("argument[2] `match`")?;
+                match s.rfind(match_s.as_str()) {
+                    Some(pos) => Ok(Value::from_usize(to_feel_index(s[..pos].chars().count()))),
+                    None => Ok(Value::NullV),
+                }
+            },
+        );
+
+        // starts with and ends with also check a list against a
+        // sublist, e.g. starts with([1, 2, 3], [1, 2])
+        self.add_native_func(
+            "starts with",
+            &["string", "match"],
+            |_, args| -> EvalResult {
+                let v = args.get(&"string".to_owned()).unwrap();
+                let mv = args.get(&"match".to_owned()).unwrap();
+                if let Value::ArrayV(_) = v {
+                    let arr = v.expect_array("argument[1] `list`")?;
+                    let match_arr = mv.expect_array("argument[2] `match`")?;
+                    return Ok(Value::BoolV(arr.starts_with(&match_arr)));
+                }
+                let s = v.expect_string("argument[1] `string`")?;
+                let match_s = mv.expect_string("argument[2] `match`")?;
                 Ok(Value::BoolV(s.starts_with(match_s.as_str())))
             },
         );
 
         self.add_native_func("ends with", &["string", "match"], |_, args| -> EvalResult {
             let v = args.get(&"string".to_owned()).unwrap();
-            let s = v.expect_string("argument[1] `string`")?;
             let mv = args.get(&"match".to_owned()).unwrap();
+            if let Value::ArrayV(_) = v {
+                let arr = v.expect_array("argument[1] `list`")?;
+                let match_arr = mv.expect_array("argument[2] `match`")?;
+                return Ok(Value::BoolV(arr.ends_with(&match_arr)));
+            }
+            let s = v.expect_string("argument[1] `string`")?;
             let match_s = mv.expect_string("argument[2] `match`")?;
             Ok(Value::BoolV(s.ends_with(match_s.as_str())))
         });
@@ -329,6 +1009,21 @@ impl Prelude {
             },
         );
 
+        self.add_native_func("truncate", &["n"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"n".to_owned()).unwrap();
+            let n = arg0.expect_number("argument[1] `n`")?;
+            Ok(Value::NumberV(n.truncate()))
+        });
+
+        // false for values other than numbers
+        self.add_native_func("is integer", &["value"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"value".to_owned()).unwrap();
+            match arg0 {
+                NumberV(n) => Ok(BoolV(n.is_integer())),
+                _ => Ok(BoolV(false)),
+            }
+        });
+
         // round down is the same as floor
         self.add_native_func_with_optional_args(
             "round down",
@@ -385,7 +1080,7 @@ impl Prelude {
                     negative: _,
                 } => Ok(Value::DurationV {
                     duration: duration.clone(),
-                    negative: true,
+                    negative: false,
                 }),
                 _ => Err(EvalError::value_error(
                     format!(
@@ -407,7 +1102,30 @@ impl Prelude {
                 let arg1 = args.get(&"divisor".to_owned()).unwrap();
                 let divisor = arg1.expect_number("argument[2] `divisor`")?;
 
-                Ok(Value::NumberV(dividend % divisor))
+                match dividend.checked_rem(divisor) {
+                    Some(v) => Ok(Value::NumberV(v)),
+                    None => Err(EvalError::value_error("division by zero")),
+                }
+            },
+        );
+
+        // percentage helpers, percent(15) is 0.15 and
+        // percent of(15, 200) is 30
+        self.add_native_func("percent", &["number"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"number".to_owned()).unwrap();
+            let n = arg0.expect_number("argument[1] `number`")?;
+            Ok(Value::NumberV(n / Numeric::from_i32(100)))
+        });
+
+        self.add_native_func(
+            "percent of",
+            &["percent", "total"],
+            |_, args| -> EvalResult {
+                let arg0 = args.get(&"percent".to_owned()).unwrap();
+                let percent = arg0.expect_number("argument[1] `percent`")?;
+                let arg1 = args.get(&"total".to_owned()).unwrap();
+                let total = arg1.expect_number("argument[2] `total`")?;
+                Ok(Value::NumberV(total * percent / Numeric::from_i32(100)))
             },
         );
 
@@ -429,30 +1147,43 @@ impl Prelude {
             None,
             |_, args| -> EvalResult {
                 let arg0 = args.get(&"number".to_owned()).unwrap();
-                let n = arg0.expect_number("argument[1] `number`")?;
-                let ln = match n.ln() {
-                    Some(v) => v,
-                    None => return Err(EvalError::value_error("log() failed")),
-                };
-
-                if let Some(arg1) = args.get(&"base".to_owned()) {
-                    let base = arg1.expect_number("argument[2] `base`")?;
-                    if base <= Numeric::ONE {
-                        Err(EvalError::value_error("argument[2] `base`, negative base"))
-                    } else if let Some(base_ln) = base.ln() {
-                        Ok(Value::NumberV(ln / base_ln))
-                    } else {
-                        Err(EvalError::value_error("log(base) failed"))
+                let n = expect_log_number(arg0)?;
+                match args.get(&"base".to_owned()) {
+                    Some(arg1) => {
+                        let base = arg1.expect_number("argument[2] `base`")?;
+                        log_of(&n, Some(&base))
                     }
-                } else {
-                    Ok(Value::NumberV(ln))
+                    None => log_of(&n, None),
                 }
             },
         );
 
-        self.add_native_func("odd", &["number"], |_, args| -> EvalResult {
+        self.add_native_func("log10", &["number"], |_, args| -> EvalResult {
             let arg0 = args.get(&"number".to_owned()).unwrap();
-            let n = arg0.expect_number("argument[1] `number`")?;
+            let n = expect_log_number(arg0)?;
+            log_of(&n, Some(&Numeric::from_i32(10)))
+        });
+
+        self.add_native_func("log2", &["number"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"number".to_owned()).unwrap();
+            let n = expect_log_number(arg0)?;
+            log_of(&n, Some(&Numeric::TWO))
+        });
+
+        self.add_native_func("exp", &["number"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"number".to_owned()).unwrap();
+            let n = arg0.expect_number("argument[1] `number`")?;
+            match n.exp() {
+                Some(v) => Ok(Value::NumberV(v)),
+                None => Err(EvalError::value_error(
+                    "argument[1] `number`, exp() overflows",
+                )),
+            }
+        });
+
+        self.add_native_func("odd", &["number"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"number".to_owned()).unwrap();
+            let n = arg0.expect_number("argument[1] `number`")?;
             Ok(Value::BoolV(
                 n.is_integer() && n % Numeric::TWO == Numeric::ONE,
             ))
@@ -474,6 +1205,52 @@ impl Prelude {
 
         // list functions
         // refer to https://docs.camunda.io/docs/components/modeler/feel/builtin-functions/feel-built-in-functions-list/
+        // join any number of strings, e.g. concat("a", "b") is "ab"
+        self.add_native_func_with_optional_args(
+            "concat",
+            &[],
+            &[],
+            Some("strings"),
+            |_, args| -> EvalResult {
+                let arg0 = args.get(&"strings".to_owned()).unwrap();
+                let arr = arg0.expect_array("arguments `strings`")?;
+                let mut res = String::new();
+                for (i, v) in arr.iter().enumerate() {
+                    match v {
+                        StrV(s) => res.push_str(s),
+                        _ => {
+                            return Err(EvalError::type_error(
+                                format!(
+                                    "string at argument[{}], but {} found",
+                                    i + 1,
+                                    v.data_type()
+                                )
+                                .as_str(),
+                            ))
+                        }
+                    }
+                }
+                Ok(StrV(res))
+            },
+        );
+
+        // whether the sublist appears in the list as consecutive elements
+        self.add_native_func(
+            "contains sublist",
+            &["list", "sublist"],
+            |_, args| -> EvalResult {
+                let v = args.get(&"list".to_owned()).unwrap();
+                let arr = v.expect_array("argument[1] `list`")?;
+                let sv = args.get(&"sublist".to_owned()).unwrap();
+                let sub = sv.expect_array("argument[2] `sublist`")?;
+                if sub.is_empty() {
+                    return Ok(Value::BoolV(true));
+                }
+                let found = arr.windows(sub.len()).any(|w| w == &sub[..]);
+                Ok(Value::BoolV(found))
+            },
+        );
+
         self.add_native_func(
             "list contains",
             &["list", "element"],
@@ -497,68 +1274,39 @@ impl Prelude {
             &[],
             Some("list"),
             |_, args| -> EvalResult {
-                let v = args.get(&"list".to_owned()).unwrap();
-                let arr = v.expect_array("arguments `list`")?;
+                // a single list argument is counted by its elements like
+                // the other aggregations, e.g. count([1, 2]) is 2
+                let arr = aggregate_list(&args)?;
                 let count = Numeric::from_usize(arr.len());
                 Ok(Value::NumberV(count))
             },
         );
 
-        self.add_native_func_with_optional_args(
-            "min",
-            &[],
-            &[],
-            Some("list"),
-            |_, args| -> EvalResult {
-                let arg0 = args.get(&"list".to_owned()).unwrap();
-                let arr = arg0.expect_array("arguments `list`")?;
-                let mut min_value: Option<Value> = None;
-
-                for v in arr.iter() {
-                    if min_value.is_none() || *v < min_value.clone().unwrap() {
-                        min_value = Some(v.clone())
-                    }
-                }
-                Ok(min_value.unwrap_or(Value::NullV))
-            },
-        );
-
-        self.add_native_func_with_optional_args(
-            "max",
-            &[],
-            &[],
-            Some("list"),
-            |_, args| -> EvalResult {
-                let arg0 = args.get(&"list".to_owned()).unwrap();
-                let arr = arg0.expect_array("arguments `list`")?;
-                let mut max_value: Option<Value> = None;
+        // aggregations skip non-number elements by default, the strict
+        // variants raise a TypeError naming the offending element instead
+        self.add_native_func_with_optional_args("min", &[], &[], Some("list"), |_, args| {
+            aggregate_min_max(&aggregate_list(&args)?, cmp::Ordering::Less, false)
+        });
+        self.add_native_func_with_optional_args("strict min", &[], &[], Some("list"), |_, args| {
+            aggregate_min_max(&aggregate_list(&args)?, cmp::Ordering::Less, true)
+        });
 
-                for v in arr.iter() {
-                    if max_value.is_none() || *v > max_value.clone().unwrap() {
-                        max_value = Some(v.clone())
-                    }
-                }
-                Ok(max_value.unwrap_or(Value::NullV))
-            },
-        );
+        self.add_native_func_with_optional_args("max", &[], &[], Some("list"), |_, args| {
+            aggregate_min_max(&aggregate_list(&args)?, cmp::Ordering::Greater, false)
+        });
+        self.add_native_func_with_optional_args("strict max", &[], &[], Some("list"), |_, args| {
+            aggregate_min_max(&aggregate_list(&args)?, cmp::Ordering::Greater, true)
+        });
 
+        self.add_native_func_with_optional_args("sum", &[], &[], Some("list"), |eng, args| {
+            aggregate_sum(eng, &aggregate_list(&args)?, false)
+        });
         self.add_native_func_with_optional_args(
-            "sum",
+            "strict sum",
             &[],
             &[],
             Some("list"),
-            |_, args| -> EvalResult {
-                let arg0 = args.get(&"list".to_owned()).unwrap();
-                let arr = arg0.expect_array("arguments `list`")?;
-                let mut sum: Numeric = Numeric::ZERO;
-
-                for v in arr.iter() {
-                    if let Value::NumberV(v) = v {
-                        sum += v.clone();
-                    }
-                }
-                Ok(Value::NumberV(sum))
-            },
+            |eng, args| aggregate_sum(eng, &aggregate_list(&args)?, true),
         );
 
         self.add_native_func_with_optional_args(
@@ -566,9 +1314,12 @@ impl Prelude {
             &[],
             &[],
             Some("list"),
-            |_, args| -> EvalResult {
-                let arg0 = args.get(&"list".to_owned()).unwrap();
-                let arr = arg0.expect_array("arguments `list`")?;
+            |eng, args| -> EvalResult {
+                let arr = aggregate_list(&args)?;
+                // 1 for an empty list or null following DMN 1.2
+                if arr.is_empty() && eng.dialect() == Dialect::Dmn12 {
+                    return Ok(Value::NullV);
+                }
                 let mut res = Numeric::ONE;
 
                 for v in arr.iter() {
@@ -580,64 +1331,42 @@ impl Prelude {
             },
         );
 
+        self.add_native_func_with_optional_args("mean", &[], &[], Some("list"), |_, args| {
+            aggregate_mean(&aggregate_list(&args)?, false)
+        });
         self.add_native_func_with_optional_args(
-            "mean",
+            "strict mean",
             &[],
             &[],
             Some("list"),
-            |_, args| -> EvalResult {
-                let arg0 = args.get(&"list".to_owned()).unwrap();
-                let arr = arg0.expect_array("arguments `list`")?;
-                let mut sum = Numeric::ZERO;
-                let mut count = 0;
-
-                for v in arr.iter() {
-                    if let Value::NumberV(v) = v {
-                        sum += v.clone();
-                        count += 1;
-                    }
-                }
-                if count == 0 {
-                    Ok(Value::NullV)
-                } else {
-                    let cnt = Numeric::from_i32(count);
-                    Ok(Value::NumberV(sum / cnt))
-                }
-            },
+            |_, args| aggregate_mean(&aggregate_list(&args)?, true),
         );
 
-        self.add_native_func_with_optional_args(
-            "stddev",
-            &[],
-            &[],
-            Some("list"),
-            |_, args| -> EvalResult {
-                let arg0 = args.get(&"list".to_owned()).unwrap();
-                let arr = arg0.expect_array("arguments `list`")?;
-                let mut sum = Numeric::ZERO;
-                let mut count = 0;
-                for v in arr.iter() {
-                    if let Value::NumberV(v) = v {
-                        sum += v.clone();
-                        count += 1;
-                    }
-                }
-                if count == 0 {
-                    return Ok(Value::NullV);
-                }
-                let avg = sum / Numeric::from_i32(count);
+        // `sample` is passed by name, e.g. stddev([1, 2, 3], sample: true)
+        self.add_native_func_with_named_args("stddev", &["sample"], "list", |_, args| {
+            aggregate_stddev(&stddev_list(&args)?, false, stddev_sample(&args)?)
+        });
+        self.add_native_func_with_named_args("strict stddev", &["sample"], "list", |_, args| {
+            aggregate_stddev(&stddev_list(&args)?, true, stddev_sample(&args)?)
+        });
 
-                let mut dev = Numeric::ZERO;
-                for v in arr.iter() {
-                    if let Value::NumberV(v) = v {
-                        let diff = v.clone() - avg.clone();
-                        dev += diff.clone() * diff;
-                    }
-                }
-                dev = dev / Numeric::from_i32(count);
-                dev.sqrt().map_or(Ok(NullV), |n| Ok(NumberV(n)))
-            },
-        );
+        // aggregations over a projection of each element
+        self.add_macro("sum over", &["list", "projection"], |eng, nodes| {
+            let arr = project_list(eng, &nodes)?;
+            aggregate_sum(eng, &arr, false)
+        });
+
+        self.add_macro("mean over", &["list", "projection"], |eng, nodes| {
+            aggregate_mean(&project_list(eng, &nodes)?, false)
+        });
+
+        self.add_macro("min over", &["list", "projection"], |eng, nodes| {
+            aggregate_min_max(&project_list(eng, &nodes)?, cmp::Ordering::Less, false)
+        });
+
+        self.add_macro("max over", &["list", "projection"], |eng, nodes| {
+            aggregate_min_max(&project_list(eng, &nodes)?, cmp::Ordering::Greater, false)
+        });
 
         self.add_native_func_with_optional_args(
             "median",
@@ -669,15 +1398,80 @@ impl Prelude {
             },
         );
 
+        self.add_native_func("mode", &["list"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"list".to_owned()).unwrap();
+            let arr = arg0.expect_array("argument[1] `list`")?;
+            let mut value_arr: Vec<Numeric> = vec![];
+
+            for v in arr.iter() {
+                if let Value::NumberV(v) = v {
+                    value_arr.push(v.clone());
+                }
+            }
+            value_arr.sort();
+
+            // count the runs of the sorted values
+            let mut runs: Vec<(Numeric, usize)> = vec![];
+            for v in value_arr.into_iter() {
+                match runs.last_mut() {
+                    Some((last, count)) if *last == v => *count += 1,
+                    _ => runs.push((v, 1)),
+                }
+            }
+            let max_count = runs.iter().map(|(_, c)| *c).max().unwrap_or(0);
+            let res = runs
+                .into_iter()
+                .filter(|(_, c)| *c == max_count)
+                .map(|(v, _)| NumberV(v))
+                .collect();
+            Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+        });
+
+        self.add_native_func("percentile", &["list", "p"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"list".to_owned()).unwrap();
+            let arr = arg0.expect_array("argument[1] `list`")?;
+            let arg1 = args.get(&"p".to_owned()).unwrap();
+            let p = arg1.expect_number("argument[2] `p`")?;
+            if p < Numeric::ZERO || p > Numeric::ONE {
+                return Err(EvalError::value_error(
+                    "argument[2] `p`, expect number between 0 and 1",
+                ));
+            }
+            let mut value_arr: Vec<Numeric> = vec![];
+
+            for v in arr.iter() {
+                if let Value::NumberV(v) = v {
+                    value_arr.push(v.clone());
+                }
+            }
+            if value_arr.is_empty() {
+                return Err(EvalError::value_error(
+                    "argument[1] `list`, expect non empty number list",
+                ));
+            }
+            value_arr.sort();
+
+            // linear interpolation between the closest ranks
+            let rank = p * Numeric::from_usize(value_arr.len() - 1);
+            let low = rank.floor();
+            let low_idx = low.to_usize().unwrap_or(0);
+            let high_idx = cmp::min(low_idx + 1, value_arr.len() - 1);
+            let frac = rank - low;
+            let low_value = value_arr[low_idx].clone();
+            let high_value = value_arr[high_idx].clone();
+            Ok(NumberV(low_value.clone() + (high_value - low_value) * frac))
+        });
+
         self.add_native_func_with_optional_args(
             "all",
             &[],
             &[],
             Some("list"),
-            |_, args| -> EvalResult {
-                let arg0 = args.get(&"list".to_owned()).unwrap();
-                let arr = arg0.expect_array("arguments `list`")?;
-
+            |eng, args| -> EvalResult {
+                let arr = aggregate_list(&args)?;
+                if eng.dialect() == Dialect::Dmn12 {
+                    return Ok(all_or_any(&arr, false));
+                }
                 for v in arr.iter() {
                     if !v.bool_value() {
                         return Ok(BoolV(false));
@@ -692,10 +1486,11 @@ impl Prelude {
             &[],
             &[],
             Some("list"),
-            |_, args| -> EvalResult {
-                let arg0 = args.get(&"list".to_owned()).unwrap();
-                let arr = arg0.expect_array("arguments `list`")?;
-
+            |eng, args| -> EvalResult {
+                let arr = aggregate_list(&args)?;
+                if eng.dialect() == Dialect::Dmn12 {
+                    return Ok(all_or_any(&arr, true));
+                }
                 for v in arr.iter() {
                     if v.bool_value() {
                         return Ok(BoolV(true));
@@ -715,11 +1510,17 @@ impl Prelude {
                 let arr = arg0.expect_array("argument[1] `list`")?;
 
                 let start_v = args.get(&"start position".to_owned()).unwrap();
-                let feel_start_position = range_check(
-                    start_v.expect_usize("argument[2] `start position`")?,
-                    1,
-                    arr.len(),
-                )?;
+                let start_position = start_v.expect_integer("argument[2] `start position`")?;
+                // a negative start position counts from the end
+                let start_position = if start_position < 0 {
+                    arr.len() as isize + start_position + 1
+                } else {
+                    start_position
+                };
+                if start_position < 1 {
+                    return Err(EvalError::index_error());
+                }
+                let feel_start_position = range_check(start_position as usize, 1, arr.len())?;
                 // 'length' is the optional value
                 let start_pos = from_feel_index(feel_start_position);
                 let subarr = if let Some(lenv) = args.get(&"length".to_owned()) {
@@ -775,30 +1576,69 @@ impl Prelude {
             },
         );
 
-        self.add_native_func("flatten", &["list"], |_, args| -> EvalResult {
+        // flatten nested lists fully, or up to `depth` levels when a
+        // positive depth is given
+        self.add_native_func_with_optional_args(
+            "flatten",
+            &["list"],
+            &["depth"],
+            None,
+            |_, args| -> EvalResult {
+                let arg0 = args.get(&"list".to_owned()).unwrap();
+                let arr = arg0.expect_array("argument[1] `list`")?;
+                let depth = match args.get(&"depth".to_owned()) {
+                    Some(v) => v.expect_usize("argument[2] `depth`")?,
+                    None => 0,
+                };
+                let max_level = if depth == 0 { MAX_FLATTEN_DEPTH } else { depth };
+                let mut res: Vec<Value> = vec![];
+                flatten_into(&arr, max_level, depth == 0, &mut res)?;
+                Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+            },
+        );
+
+        // count the elements by the key the function returns, keys
+        // other than strings are converted to strings
+        self.add_native_func(
+            "count by",
+            &["list", "function"],
+            |eng, args| -> EvalResult {
+                let arg0 = args.get(&"list".to_owned()).unwrap();
+                let arr = arg0.expect_array("argument[1] `list`")?;
+                let key_func = args.get(&"function".to_owned()).unwrap();
+
+                let mut counts = Context::new();
+                for v in arr.iter() {
+                    let key = match eng.call_function(key_func, vec![v.clone()])? {
+                        Value::StrV(s) => s,
+                        k => k.to_string(),
+                    };
+                    let n = match counts.get(key.clone()) {
+                        Some(Value::NumberV(n)) => n + Numeric::ONE,
+                        _ => Numeric::ONE,
+                    };
+                    counts.insert(key, Value::NumberV(n));
+                }
+                Ok(Value::ContextV(Rc::new(RefCell::new(counts))))
+            },
+        );
+
+        self.add_native_func("sort", &["list"], |_, args| -> EvalResult {
             let arg0 = args.get(&"list".to_owned()).unwrap();
             let arr = arg0.expect_array("argument[1] `list`")?;
 
-            let mut res: Vec<Value> = vec![];
-            for v in arr.iter() {
-                match v {
-                    Value::ArrayV(a) => {
-                        for x in a.as_ref().borrow().iter() {
-                            res.push(x.clone());
-                        }
-                    }
-                    x => res.push(x.clone()),
-                }
-            }
+            let mut res: Vec<Value> = arr.iter().map(|x| x.clone()).collect();
+            res.sort();
             Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
         });
 
-        self.add_native_func("sort", &["list"], |_, args| -> EvalResult {
+        // equal elements keep their order, unlike reverse(sort(list))
+        self.add_native_func("sort descending", &["list"], |_, args| -> EvalResult {
             let arg0 = args.get(&"list".to_owned()).unwrap();
             let arr = arg0.expect_array("argument[1] `list`")?;
 
             let mut res: Vec<Value> = arr.iter().map(|x| x.clone()).collect();
-            res.sort();
+            res.sort_by(|a, b| b.cmp(a));
             Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
         });
 
@@ -848,6 +1688,32 @@ impl Prelude {
             Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
         });
 
+        self.add_native_func("first", &["list"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"list".to_owned()).unwrap();
+            let arr = arg0.expect_array("argument[1] `list`")?;
+            Ok(arr.first().cloned().unwrap_or(Value::NullV))
+        });
+
+        self.add_native_func("last", &["list"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"list".to_owned()).unwrap();
+            let arr = arg0.expect_array("argument[1] `list`")?;
+            Ok(arr.last().cloned().unwrap_or(Value::NullV))
+        });
+
+        // the last element satisfying the predicate, or null
+        self.add_native_func("find last", &["list", "match"], |eng, args| -> EvalResult {
+            let arg0 = args.get(&"list".to_owned()).unwrap();
+            let arr = arg0.expect_array("argument[1] `list`")?.clone();
+            let predicate = args.get(&"match".to_owned()).unwrap();
+
+            for v in arr.into_iter().rev() {
+                if eng.call_function(predicate, vec![v.clone()])?.bool_value() {
+                    return Ok(v);
+                }
+            }
+            Ok(Value::NullV)
+        });
+
         self.add_native_func("index of", &["list", "match"], |_, args| -> EvalResult {
             let arg0 = args.get(&"list".to_owned()).unwrap();
             let arr = arg0.expect_array("argument[1] `list`")?;
@@ -865,11 +1731,140 @@ impl Prelude {
             Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
         });
 
+        self.add_native_func(
+            "first index of",
+            &["list", "match"],
+            |_, args| -> EvalResult {
+                let arg0 = args.get(&"list".to_owned()).unwrap();
+                let arr = arg0.expect_array("argument[1] `list`")?;
+
+                let arg1 = args.get(&"match".to_owned()).unwrap();
+                match arr.iter().position(|v| *v == *arg1) {
+                    Some(i) => Ok(Value::from_usize(to_feel_index(i))),
+                    None => Ok(Value::NullV),
+                }
+            },
+        );
+
         self.add_native_func("distinct values", &["list"], |_, args| -> EvalResult {
             let arg0 = args.get(&"list".to_owned()).unwrap();
             let arr = arg0.expect_array("argument[1] `list`")?;
-            let mut res: Vec<Value> = arr.iter().map(|x| x.clone()).collect();
-            res.dedup();
+            let res = distinct_values(arr.iter());
+            Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+        });
+
+        // keep the last occurrence of each value, in the order of
+        // their last appearances
+        self.add_native_func("distinct values last", &["list"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"list".to_owned()).unwrap();
+            let arr = arg0.expect_array("argument[1] `list`")?;
+            let mut res = distinct_values(arr.iter().rev());
+            res.reverse();
+            Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+        });
+
+        // pair each element with its 1-based index as {index, value}
+        // contexts, e.g. for x in zip with index(l) return x.index
+        self.add_native_func("zip with index", &["list"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"list".to_owned()).unwrap();
+            let arr = arg0.expect_array("argument[1] `list`")?;
+            let res: Vec<Value> = arr
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    let mut item_ctx = Context::new();
+                    item_ctx.insert("index".to_string(), Value::from_usize(to_feel_index(i)));
+                    item_ctx.insert("value".to_string(), v.clone());
+                    Value::ContextV(Rc::new(RefCell::new(item_ctx)))
+                })
+                .collect();
+            Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+        });
+
+        // the differences of consecutive numbers, one less than the list
+        self.add_native_func("differences", &["list"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"list".to_owned()).unwrap();
+            let arr = arg0.expect_array("argument[1] `list`")?;
+            let mut numbers: Vec<Numeric> = vec![];
+            for v in arr.iter() {
+                numbers.push(v.expect_number("element of argument[1] `list`")?);
+            }
+            let res: Vec<Value> = numbers
+                .windows(2)
+                .map(|pair| Value::NumberV(pair[1].clone() - pair[0].clone()))
+                .collect();
+            Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+        });
+
+        // apply the function to each pair of adjacent elements
+        self.add_native_func(
+            "pairwise",
+            &["list", "function"],
+            |eng, args| -> EvalResult {
+                let arg0 = args.get(&"list".to_owned()).unwrap();
+                let arr = arg0.expect_array("argument[1] `list`")?.clone();
+                let func = args.get(&"function".to_owned()).unwrap();
+
+                let mut res: Vec<Value> = vec![];
+                for pair in arr.windows(2) {
+                    res.push(eng.call_function(func, pair.to_vec())?);
+                }
+                Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+            },
+        );
+
+        // consecutive runs of equal values as {value, count} contexts
+        self.add_native_func("run length encode", &["list"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"list".to_owned()).unwrap();
+            let arr = arg0.expect_array("argument[1] `list`")?;
+            let mut runs: Vec<(Value, usize)> = vec![];
+            for v in arr.iter() {
+                match runs.last_mut() {
+                    Some((last, count)) if last == v => *count += 1,
+                    _ => runs.push((v.clone(), 1)),
+                }
+            }
+            let res: Vec<Value> = runs
+                .into_iter()
+                .map(|(v, count)| {
+                    let mut run_ctx = Context::new();
+                    run_ctx.insert("value".to_string(), v);
+                    run_ctx.insert("count".to_string(), Value::from_usize(count));
+                    Value::ContextV(Rc::new(RefCell::new(run_ctx)))
+                })
+                .collect();
+            Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+        });
+
+        self.add_native_func("run length decode", &["list"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"list".to_owned()).unwrap();
+            let arr = arg0.expect_array("argument[1] `list`")?;
+            let mut res: Vec<Value> = vec![];
+            for (i, run_v) in arr.iter().enumerate() {
+                let hint = format!("argument[1][{}]", i + 1);
+                let run_ctx = run_v.expect_context(hint.as_str())?;
+                let v = run_ctx
+                    .get("value".to_owned())
+                    .ok_or(EvalError::value_error(
+                        format!("{}, `value` is missing", hint).as_str(),
+                    ))?;
+                let count = run_ctx
+                    .get("count".to_owned())
+                    .ok_or(EvalError::value_error(
+                        format!("{}, `count` is missing", hint).as_str(),
+                    ))?;
+                let count = count.expect_usize(format!("{}.count", hint).as_str())?;
+                if count > MAX_DECODED_LEN - res.len() {
+                    return Err(EvalError::value_error(
+                        format!(
+                            "run length decode exceeds the max length {}",
+                            MAX_DECODED_LEN
+                        )
+                        .as_str(),
+                    ));
+                }
+                res.extend(std::iter::repeat_n(v, count));
+            }
             Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
         });
 
@@ -886,21 +1881,57 @@ impl Prelude {
                     let childlist = v.expect_array(format!("argument[{}]", (i + 1)).as_str())?;
                     lists.push(childlist.iter().map(|v| v.clone()).collect());
                 }
-                let mut res = lists.concat();
-                res.dedup();
+                let res = distinct_values(lists.concat().iter());
+                Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+            },
+        );
+
+        // cartesian product of lists, e.g. cartesian([1,2], [3,4]) gives
+        // [[1,3], [1,4], [2,3], [2,4]], no lists gives an empty list
+        self.add_native_func_with_optional_args(
+            "cartesian",
+            &[],
+            &[],
+            Some("lists"),
+            |_, args| -> EvalResult {
+                let arg0 = args.get(&"lists".to_owned()).unwrap();
+                let arr = arg0.expect_array("arguments `lists`")?;
+                if arr.is_empty() {
+                    return Ok(Value::ArrayV(Rc::new(RefCell::new(vec![]))));
+                }
+                let mut products: Vec<Vec<Value>> = vec![vec![]];
+                for (i, v) in arr.iter().enumerate() {
+                    let childlist = v.expect_array(format!("argument[{}]", (i + 1)).as_str())?;
+                    let mut next_products: Vec<Vec<Value>> = vec![];
+                    for prefix in products.iter() {
+                        for item in childlist.iter() {
+                            let mut tuple = prefix.clone();
+                            tuple.push(item.clone());
+                            next_products.push(tuple);
+                        }
+                    }
+                    products = next_products;
+                }
+                let res: Vec<Value> = products
+                    .into_iter()
+                    .map(|tuple| Value::ArrayV(Rc::new(RefCell::new(tuple))))
+                    .collect();
                 Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
             },
         );
 
         // context/map functions
         // refer to https://docs.camunda.io/docs/components/modeler/feel/builtin-functions/feel-built-in-functions-context/
+        // the key can be a string list as a path, a string with dots
+        // like "a.b.c" is also split into a path, keys containing literal
+        // dots require the list form, e.g. ["a.b"]
         self.add_native_func("get value", &["context", "key"], |_, args| -> EvalResult {
             let arg0 = args.get(&"context".to_owned()).unwrap();
             let m = arg0.expect_context("argument[1] `context`")?;
 
             let arg1 = args.get(&"key".to_owned()).unwrap();
             let path = match arg1.clone() {
-                Value::StrV(s) => vec![s],
+                Value::StrV(s) => s.split('.').map(|k| k.to_owned()).collect(),
                 Value::ArrayV(a) => {
                     let mut keys = vec![];
                     for (i, v) in a.as_ref().borrow().iter().enumerate() {
@@ -939,10 +1970,58 @@ impl Prelude {
             Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
         });
 
+        // keys and values are in the same order as `get entries`, that
+        // is sorted by key as a context does not keep the order of
+        // insertion, e.g. get keys({b: 2, a: 1}) is ["a", "b"]
+        self.add_native_func("get keys", &["context"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"context".to_owned()).unwrap();
+            let m = arg0.expect_context("argument[1] `context`")?;
+            let res: Vec<Value> = m
+                .entries()
+                .into_iter()
+                .map(|(k, _)| Value::StrV(k))
+                .collect();
+            Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+        });
+
+        self.add_native_func("get values", &["context"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"context".to_owned()).unwrap();
+            let m = arg0.expect_context("argument[1] `context`")?;
+            let res: Vec<Value> = m.entries().into_iter().map(|(_, v)| v).collect();
+            Ok(Value::ArrayV(Rc::new(RefCell::new(res))))
+        });
+
+        // swap keys and values, values must be strings, numbers or
+        // booleans. when values repeat, the entry coming last in key
+        // order wins
+        self.add_native_func("invert", &["context"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"context".to_owned()).unwrap();
+            let m = arg0.expect_context("argument[1] `context`")?;
+            let mut res = Context::new();
+            for (k, v) in m.entries() {
+                let new_key = match v {
+                    StrV(s) => s,
+                    NumberV(_) | BoolV(_) => v.to_string(),
+                    _ => {
+                        return Err(EvalError::type_error(
+                            format!(
+                                "string, number or boolean at key `{}`, but {} found",
+                                k,
+                                v.data_type()
+                            )
+                            .as_str(),
+                        ))
+                    }
+                };
+                res.insert(new_key, StrV(k));
+            }
+            Ok(Value::ContextV(Rc::new(RefCell::new(res))))
+        });
+
         self.add_native_func(
             "context put",
             &["context", "key", "value"],
-            |_, args| -> EvalResult {
+            |eng, args| -> EvalResult {
                 let arg0 = args.get(&"context".to_owned()).unwrap();
                 let m = arg0.expect_context_ref("argument[1] `context`")?;
 
@@ -970,14 +2049,39 @@ impl Prelude {
                 };
 
                 let arg2 = args.get(&"value".to_owned()).unwrap();
-                m.as_ref()
-                    .borrow_mut()
-                    .insert_path(path.as_slice(), arg2.clone());
-
-                Ok(Value::ContextV(m.clone()))
+                put_path(eng, &m, path.as_slice(), arg2)
             },
         ); // end `context put`
 
+        // get and put by a dotted path string, e.g. "a.b.c", a dot
+        // inside a key is escaped as `\.` and a backslash as `\\`
+        self.add_native_func("deep get", &["context", "path"], |_, args| -> EvalResult {
+            let arg0 = args.get(&"context".to_owned()).unwrap();
+            let m = arg0.expect_context("argument[1] `context`")?;
+            let path = args
+                .get(&"path".to_owned())
+                .unwrap()
+                .expect_string("argument[2] `path`")?;
+            let path = dotted_path(path.as_str())?;
+            Ok(m.get_path(path.as_slice()).unwrap_or(Value::NullV))
+        });
+
+        self.add_native_func(
+            "deep put",
+            &["context", "path", "value"],
+            |eng, args| -> EvalResult {
+                let arg0 = args.get(&"context".to_owned()).unwrap();
+                let m = arg0.expect_context_ref("argument[1] `context`")?;
+                let path = args
+                    .get(&"path".to_owned())
+                    .unwrap()
+                    .expect_string("argument[2] `path`")?;
+                let path = dotted_path(path.as_str())?;
+                let value = args.get(&"value".to_owned()).unwrap();
+                put_path(eng, &m, path.as_slice(), value)
+            },
+        );
+
         self.add_native_func("context merge", &["contexts"], |_, args| -> EvalResult {
             let arg0 = args.get(&"contexts".to_owned()).unwrap();
             let contexts = arg0.expect_array("argument[1] `contexts`")?;
@@ -1,6 +1,7 @@
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::cmp;
 use std::error::Error;
 use std::fmt;
 
@@ -74,15 +75,44 @@ impl TextPosition {
         }
     }
 
+    // render the line at the position prefixed by its line number and
+    // a caret under the column, tabs are expanded to TAB_WIDTH spaces so
+    // that the caret stays aligned
     pub fn line_pointers(&self, full_text: &str) -> String {
-        let lines: Vec<&str> = full_text.split("\n").collect();
-        let spaces = if self.cols > 0 {
-            " ".repeat(self.cols - 1)
+        let line = full_text.split("\n").nth(self.lines).unwrap_or("");
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let mut cols = cmp::min(self.cols, line.len());
+        while !line.is_char_boundary(cols) {
+            cols -= 1;
+        }
+        let prefix = format!("{} | ", self.lines + 1);
+        let caret_offset = expand_tabs(&line[..cols]).chars().count();
+        format!(
+            "{}{}\n{}^\n",
+            prefix,
+            expand_tabs(line),
+            " ".repeat(prefix.len() + caret_offset)
+        )
+    }
+}
+
+const TAB_WIDTH: usize = 4;
+
+// replace tabs with spaces up to the next tab stop
+fn expand_tabs(text: &str) -> String {
+    let mut res = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        if c == '\t' {
+            let n = TAB_WIDTH - width % TAB_WIDTH;
+            res.push_str(&" ".repeat(n));
+            width += n;
         } else {
-            "".to_string()
-        };
-        format!("{}\n{}^\n", lines[self.lines], spaces)
+            res.push(c);
+            width += 1;
+        }
     }
+    res
 }
 
 // Token struct
@@ -183,6 +213,92 @@ fn test_value_ahead_03() {
     assert_eq!(cursor.cols, 5);
 }
 
+#[test]
+fn test_scan_unicode_names() {
+    let mut scanner = Scanner::new("café + 名前 * ñame_2");
+    let mut tokens: Vec<(&str, String)> = vec![];
+    loop {
+        scanner.next_token().unwrap();
+        let token = scanner.current_token();
+        if token.kind == "eof" {
+            break;
+        }
+        tokens.push((token.kind, token.value));
+    }
+    assert_eq!(
+        tokens,
+        vec![
+            ("name", "café".to_owned()),
+            ("+", "+".to_owned()),
+            ("name", "名前".to_owned()),
+            ("*", "*".to_owned()),
+            ("name", "ñame_2".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn test_scan_decimal_points() {
+    let values = |input: &str| -> Vec<String> {
+        let tokens = Scanner::new(input).find_tokens().unwrap();
+        tokens
+            .iter()
+            .map(|t| format!("{} {}", t.kind, t.value))
+            .collect()
+    };
+    assert_eq!(values(".5"), vec!["number .5"]);
+    assert_eq!(values("5.5"), vec!["number 5.5"]);
+    assert_eq!(values("5..6"), vec!["number 5", ".. ..", "number 6"]);
+    assert_eq!(values("[1..5]")[2], ".. ..");
+    assert_eq!(values("x.y"), vec!["name x", ". .", "name y"]);
+
+    for input in ["5.", "5. + 1", "[1, 2.]"] {
+        let err = Scanner::new(input).find_tokens().unwrap_err();
+        assert!(
+            err.message
+                .contains("has no digits after the decimal point"),
+            "input: {}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_find_tokens() {
+    let tokens = Scanner::new("a + 2 // comment\n").find_tokens().unwrap();
+    let kinds: Vec<&str> = tokens.iter().map(|t| t.kind).collect();
+    assert_eq!(kinds, vec!["name", "+", "number"]);
+    assert_eq!(tokens[2].to_string(), "[4..5] number '2'");
+    assert!(Scanner::new("a # b").find_tokens().is_err());
+}
+
+#[test]
+fn test_line_pointers() {
+    let text = "1 +\n\t2 * x";
+    let pos = TextPosition::zero().increase("1 +\n\t2 * ");
+    assert_eq!(pos.line_pointers(text), "2 |     2 * x\n            ^\n");
+
+    let text = "a\tb\tc";
+    let pos = TextPosition::zero().increase("a\tb\t");
+    assert_eq!(pos.line_pointers(text), "1 | a   b   c\n            ^\n");
+
+    let text = "x\ny\nname + 1";
+    let pos = TextPosition::zero().increase("x\ny\n");
+    assert_eq!(pos.line_pointers(text), "3 | name + 1\n    ^\n");
+
+    let pos = TextPosition::zero().increase("café ");
+    assert_eq!(pos.line_pointers("café bar"), "1 | café bar\n         ^\n");
+}
+
+#[test]
+fn test_text_range() {
+    let scanner = Scanner::new(r#"a + "名前" + b"#);
+    assert_eq!(scanner.text_range(4, 12), r#""名前""#);
+    assert_eq!(scanner.text_range(15, 100), "b");
+    assert_eq!(scanner.text_range(5, 6), "");
+    assert_eq!(scanner.text_range(10, 4), "");
+}
+
 #[derive(Clone)]
 struct TokenPattern {
     token: &'static str,
@@ -208,7 +324,7 @@ lazy_static! {
 
         patterns.push(TokenPattern{
             token: "keyword",
-            reg: Some(Regex::new(r"^\b(true|false|and|or|null|function|if|then|else|loop|for|some|every|in|return|satisfies)\b").unwrap()),
+            reg: Some(Regex::new(r"^\b(true|false|and|or|null|function|if|then|else|loop|for|some|every|in|return|satisfies|between)\b").unwrap()),
         });
 
         patterns.push(TokenPattern {
@@ -227,9 +343,16 @@ lazy_static! {
             // backtick string is in camunda dialets
         });
 
+        // a number may start with the decimal point, e.g. .5, but ..5
+        // stays a range operator
+        patterns.push(TokenPattern {
+            token: "number",
+            reg: Some(Regex::new(r#"^\.[0-9]+(_[0-9]+)*\b"#).unwrap()),
+        });
+
         let ops = [
             "..", ".", ",", ";", ">=", ">", "=", "<=", "<", "!=", "!", "(", ")", "[", "]",
-            "{", "}", ":=", ":", "+", "-", "*", "/", "%",
+            "{", "}", ":=", ":", "+", "-", "**", "*", "/", "%",
         ];
         for op in ops {
             patterns.push(TokenPattern {
@@ -240,14 +363,15 @@ lazy_static! {
 
         patterns.push(TokenPattern {
             token: "number",
-            reg: Some(Regex::new(r#"^[0-9]+(\.[0-9]+)?\b"#).unwrap()),
+            reg: Some(Regex::new(r#"^[0-9]+(_[0-9]+)*(\.[0-9]+(_[0-9]+)*)?\b"#).unwrap()),
         });
 
         patterns.push(TokenPattern{
             token: "name",
             //reg: Some(Regex::new(r"^[a-zA-Z_][a-zA-Z_0-9]*( +[a-zA-Z_][a-zA-Z_0-9]*)*").unwrap()),
+            // unicode letters are allowed following the XID rules, e.g. café, 名前
             reg: Some(Regex::new(r"(?x)
-            ^[a-zA-Z_\$\?\%\p{Han}\p{Greek}\p{Bopomofo}\p{Hangul}][a-zA-Z_\$\?\%0-9\p{Han}\p{Greek}\p{Bopomofo}\p{Hangul}]*
+            ^[\p{XID_Start}_\$\?\%][\p{XID_Continue}\$\?\%]*
             ").unwrap()),
         });
 
@@ -255,6 +379,15 @@ lazy_static! {
     };
 }
 
+// a number followed by a single dot which starts neither a range
+// `..` nor an attribute name, e.g. `5.` or `5.)`
+fn dangling_decimal_point(after: &str) -> bool {
+    match after.strip_prefix('.') {
+        Some(rest) => !rest.starts_with(|c: char| c == '.' || c == '_' || c.is_alphabetic()),
+        None => false,
+    }
+}
+
 //#[derive(Clone, Copy)]
 pub struct Scanner<'a> {
     // input text
@@ -281,8 +414,13 @@ impl Scanner<'_> {
         self.cursor.chars >= self.input.len()
     }
 
+    // the text between two positions, start and end are byte offsets
+    // like TextPosition.chars, they are clamped to the input and a range
+    // not on char boundaries gives an empty string instead of panicking
     pub fn text_range(&self, start: usize, end: usize) -> String {
-        self.input[start..end].to_string()
+        let end = cmp::min(end, self.input.len());
+        let start = cmp::min(start, end);
+        self.input.get(start..end).unwrap_or("").to_string()
     }
 
     // returns current token
@@ -295,6 +433,15 @@ impl Scanner<'_> {
         self.current.clone().unwrap()
     }
 
+    // position of the current token, or the scan cursor when no
+    // token is scanned yet, e.g. the first token fails to scan
+    pub fn current_position(&self) -> TextPosition {
+        match self.current {
+            Some(ref token) => token.position.clone(),
+            None => self.cursor.clone(),
+        }
+    }
+
     // expect the current token to be kind
     pub fn expect(&self, kind: &str) -> bool {
         self.current
@@ -354,6 +501,15 @@ impl Scanner<'_> {
             if let Some(reg) = &pattern.reg {
                 if let Some(m) = reg.find(rest) {
                     assert_eq!(0, m.start());
+                    if pattern.token == "number" && dangling_decimal_point(&rest[m.end()..]) {
+                        return Err(ScanError::from_str(
+                            format!(
+                                "number {}. has no digits after the decimal point",
+                                m.as_str()
+                            )
+                            .as_str(),
+                        ));
+                    }
                     let token = Token {
                         kind: pattern.token,
                         value: m.as_str().to_owned(),
@@ -377,19 +533,32 @@ impl Scanner<'_> {
         Err(ScanError::from_str("fail to find token"))
     }
 
+    // skip one character at the cursor, used to step over text which
+    // fails to scan when recovering from errors
+    pub fn skip_char(&mut self) {
+        let rest = &self.input[(self.cursor.chars)..];
+        if let Some(c) = rest.chars().next() {
+            self.cursor = self.cursor.increase(&c.to_string());
+        }
+    }
+
     pub fn rewind(&mut self, token: Token) {
         self.cursor = token.position.increase(&token.value);
         self.current = Some(token.clone());
     }
 
-    // pub fn find_tokens(&mut self) -> Result<Vec<Token>, ScanError> {
-    //     let mut token_vecs: Vec<Token> = Vec::new();
-    //     while !self.is_eof() {
-    //         if let Err(err) = self.next_token() {
-    //             return Err(err);
-    //         }
-    //         token_vecs.push(self.current_token());
-    //     }
-    //     Ok(token_vecs)
-    // }
+    // scan all the remaining tokens, spaces and comments are skipped
+    // and the eof token is not included
+    pub fn find_tokens(&mut self) -> Result<Vec<Token>, ScanError> {
+        let mut token_vecs: Vec<Token> = Vec::new();
+        loop {
+            self.next_token()?;
+            let token = self.current_token();
+            if token.kind == "eof" {
+                break;
+            }
+            token_vecs.push(token);
+        }
+        Ok(token_vecs)
+    }
 }
@@ -2,6 +2,7 @@ use super::value::Value;
 use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -23,6 +24,19 @@ impl fmt::Display for Context {
     }
 }
 
+// entries are hashed in key order, so the hash doesn't depend on
+// the order the entries were inserted
+impl Hash for Context {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // a BTreeMap iterates in the key order already
+        state.write_usize(self.0.len());
+        for (k, v) in self.0.iter() {
+            k.hash(state);
+            v.hash(state);
+        }
+    }
+}
+
 impl Context {
     pub fn new() -> Context {
         Context(BTreeMap::new())
@@ -15,6 +15,9 @@ pub type NativeFuncBody =
 pub struct NativeFunc {
     pub name: String,
     pub body: NativeFuncBody,
+    // optional args passed by name only, e.g. `sample` of stddev whose
+    // positional args all go to the var arg
+    pub named_args: Vec<String>,
 }
 
 impl fmt::Debug for NativeFunc {
@@ -3,11 +3,20 @@ use bigdecimal::*;
 // use lazy_static::lazy_static;
 use num_bigint::Sign;
 use std::cmp;
-// use std::num::NonZeroU64;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroU64;
 use std::ops;
 use std::str::FromStr;
 
+/// the fractional digits kept by division and other arithmetic, FEEL
+/// numbers follow Decimal128 with 34 digits of precision
+pub const MAX_SCALE: i64 = 34;
+
+/// the max magnitude in digits of a power with an integer exponent,
+/// e.g. 10 ** 1000 is computed while 10 ** 1001 is not a finite number
+pub const MAX_POW_DIGITS: i64 = 1000;
+
 #[derive(Clone)]
 pub enum Numeric {
     Integer(i32),
@@ -28,9 +37,9 @@ impl fmt::Display for Numeric {
                     // FEEL Numbers are based on IEEE 754-2008 Decimal128 format, with 34 decimal digits of precision and rounding
                     // toward the nearest neighbor with ties favoring the even neighbor */
                     let scale = v.fractional_digit_count() as usize;
-                    if scale > 34 {
+                    if scale > MAX_SCALE as usize {
                         //write!(fa, "{:.*}", v.with_scale_round(34, RoundingMode::Floor))
-                        write!(f, "{:.34}", v)
+                        write!(f, "{:.*}", MAX_SCALE as usize, v)
                     } else {
                         write!(f, "{:.*}", scale, v)
                     }
@@ -63,6 +72,25 @@ impl Numeric {
     }
 
     pub fn from_str(input: &str) -> Option<Numeric> {
+        // underscores are digit separators, e.g. 1_000_000, only
+        // allowed between two digits
+        let stripped: String;
+        let input = if input.contains('_') {
+            let chars: Vec<char> = input.chars().collect();
+            for (i, c) in chars.iter().enumerate() {
+                if *c == '_'
+                    && (i == 0
+                        || !chars[i - 1].is_ascii_digit()
+                        || !chars.get(i + 1).is_some_and(|n| n.is_ascii_digit()))
+                {
+                    return None;
+                }
+            }
+            stripped = input.replace('_', "");
+            stripped.as_str()
+        } else {
+            input
+        };
         let bign = match BigDecimal::from_str(input) {
             Ok(v) => v,
             Err(_) => return None,
@@ -76,14 +104,25 @@ impl Numeric {
                 return Self::Integer(v);
             }
         }
-        if bign.fractional_digit_count() > 34 {
-            let round_bign = bign.with_scale_round(34, RoundingMode::Floor);
+        if bign.fractional_digit_count() > MAX_SCALE {
+            let round_bign = bign.with_scale_round(MAX_SCALE, RoundingMode::Floor);
             Self::Decimal(round_bign)
         } else {
             Self::Decimal(bign)
         }
     }
 
+    // the result of an arithmetic op, an integer value drops the
+    // trailing zeros so 1.5 * 2 is 3 instead of 3.0, values scaled on
+    // purpose e.g. by decimal(3, 2) are not passed here
+    fn from_arith(bign: BigDecimal) -> Numeric {
+        if bign.is_integer() {
+            Self::from_decimal(bign.with_scale(0))
+        } else {
+            Self::Decimal(bign)
+        }
+    }
+
     pub fn from_value(value: &Value) -> Option<Numeric> {
         match value {
             Value::NumberV(v) => Some(v.clone()),
@@ -100,6 +139,13 @@ impl Numeric {
         }
     }
 
+    pub fn from_i64(v: i64) -> Numeric {
+        match i32::try_from(v) {
+            Ok(v) => Self::Integer(v),
+            Err(_) => Self::Decimal(BigDecimal::from(v)),
+        }
+    }
+
     pub fn from_i32(v: i32) -> Numeric {
         Self::Integer(v)
     }
@@ -126,12 +172,99 @@ impl Numeric {
         self.to_decimal().sqrt().map(|n| Self::from_decimal(n))
     }
 
+    // natural logarithm, None for a non-positive number or a number
+    // too large or too small for f64, e.g. 10 ** 400
     pub fn ln(&self) -> Option<Numeric> {
         let n = self.to_decimal();
         if n <= BigDecimal::zero() {
             return None;
         }
-        n.to_f64().map(|f| Numeric::from_f64(f.ln()))
+        let f = n.to_f64()?.ln();
+        if f.is_finite() {
+            Some(Numeric::from_f64(f))
+        } else {
+            None
+        }
+    }
+
+    // logarithm to the base, None for a non-positive number or a base
+    // that is non-positive or 1
+    pub fn log(&self, base: &Numeric) -> Option<Numeric> {
+        if *base == Self::ONE {
+            return None;
+        }
+        let ln = self.ln()?;
+        let base_ln = base.ln()?;
+        Some(ln / base_ln)
+    }
+
+    pub fn exp(&self) -> Option<Numeric> {
+        let f = self.to_decimal().to_f64()?.exp();
+        if f.is_finite() {
+            Some(Numeric::from_f64(f))
+        } else {
+            None
+        }
+    }
+
+    // raise to the power. an integer exponent is multiplied out
+    // exactly as long as the result is within 10 ** MAX_POW_DIGITS, a
+    // larger result is None. a negative exponent raises the reciprocal
+    // of the base. other exponents go through f64. None for a zero
+    // base with a negative exponent or a result that is not a finite
+    // number
+    pub fn pow(&self, exponent: &Numeric) -> Option<Numeric> {
+        if let Some(e) = exponent.to_isize() {
+            let mut base = self.to_decimal();
+            if e < 0 {
+                if base.is_zero() {
+                    return None;
+                }
+                base = BigDecimal::one() / base;
+            }
+            // the digits of the result, negative for a result below 1
+            let digits = base.abs().to_f64()?.log10() * e.unsigned_abs() as f64;
+            if digits > MAX_POW_DIGITS as f64 {
+                return None;
+            }
+            let mut res = BigDecimal::one();
+            let mut n = e.unsigned_abs();
+            // the fractional digits of the intermediate products are
+            // rounded to twice the kept scale so the digits of a
+            // decimal base do not double on each squaring
+            let round = |v: BigDecimal| {
+                if v.fractional_digit_count() > MAX_SCALE * 2 {
+                    v.with_scale_round(MAX_SCALE * 2, RoundingMode::HalfEven)
+                } else {
+                    v
+                }
+            };
+            loop {
+                if n & 1 == 1 {
+                    res = round(res * base.clone());
+                }
+                n >>= 1;
+                if n == 0 {
+                    break;
+                }
+                base = round(base.clone() * base);
+            }
+            // an integer result drops the fractional zeros, e.g. from
+            // 0.1 ** -30
+            if res.is_integer() {
+                res = res.with_scale(0);
+            }
+            return Some(Self::from_decimal(res));
+        }
+        let f = self
+            .to_decimal()
+            .to_f64()?
+            .powf(exponent.to_decimal().to_f64()?);
+        if f.is_finite() {
+            Some(Numeric::from_f64(f))
+        } else {
+            None
+        }
     }
 
     pub fn is_integer(&self) -> bool {
@@ -152,6 +285,12 @@ impl Numeric {
         self.with_scale_down(0)
     }
 
+    // drop the fractional part toward zero, e.g. -1.5 is -1
+    pub fn truncate(&self) -> Numeric {
+        let v = self.to_decimal();
+        Self::from_decimal(v.with_scale_round(0, RoundingMode::Down))
+    }
+
     pub fn with_scale_down(&self, scale: i64) -> Numeric {
         let v = self.to_decimal();
         if v.sign() == Sign::Minus {
@@ -170,28 +309,56 @@ impl Numeric {
         }
     }
 
+    // round half even to the scale, a positive scale is kept even for
+    // whole values, e.g. 3 with scale 2 is 3.00
     pub fn with_scale_even(&self, scale: i64) -> Numeric {
-        let v = self.to_decimal();
-        Self::from_decimal(v.with_scale_round(scale, RoundingMode::HalfEven))
+        let v = self
+            .to_decimal()
+            .with_scale_round(scale, RoundingMode::HalfEven);
+        if scale > 0 && scale <= MAX_SCALE {
+            Self::Decimal(v)
+        } else {
+            Self::from_decimal(v)
+        }
     }
 
+    /// format the number with exactly `scale` fractional digits, the
+    /// value is rounded half even and trailing zeros are kept,
+    /// e.g. 1.5 with scale 3 is "1.500"
+    pub fn to_fixed_string(&self, scale: usize) -> String {
+        let v = self
+            .to_decimal()
+            .with_scale_round(scale as i64, RoundingMode::HalfEven);
+        format!("{:.*}", scale, v)
+    }
+
+    /// format the number with `digits` significant digits, the value
+    /// is rounded half even and trailing zeros are kept, e.g. 1.5 with
+    /// 4 digits is "1.500" and 12345 with 2 digits is "12000"
+    pub fn to_significant_string(&self, digits: u64) -> String {
+        let prec = NonZeroU64::new(digits).unwrap_or(NonZeroU64::MIN);
+        let v = self
+            .to_decimal()
+            .with_precision_round(prec, RoundingMode::HalfEven);
+        let scale = cmp::max(v.fractional_digit_count(), 0) as usize;
+        format!("{:.*}", scale, v.with_scale(scale as i64))
+    }
+
+    // None for negative or fractional values and values beyond usize
     pub fn to_usize(&self) -> Option<usize> {
         match self {
-            Self::Integer(v) => {
-                if *v > 0 {
-                    Some(*v as usize)
-                } else {
-                    None
-                }
-            }
-            Self::Decimal(v) => v.to_usize(),
+            Self::Integer(v) => usize::try_from(*v).ok(),
+            Self::Decimal(v) if v.is_integer() => v.to_usize(),
+            Self::Decimal(_) => None,
         }
     }
 
+    // None for fractional values and values beyond isize
     pub fn to_isize(&self) -> Option<isize> {
         match self {
             Self::Integer(v) => Some(*v as isize),
-            Self::Decimal(v) => v.to_isize(),
+            Self::Decimal(v) if v.is_integer() => v.to_isize(),
+            Self::Decimal(_) => None,
         }
     }
 }
@@ -209,16 +376,16 @@ macro_rules! complex_op {
                 },
                 Self::Decimal(b) => {
                     let r = BigDecimal::from_i32(a).unwrap() $op b;
-                    Self::Decimal(r)
+                    Self::from_arith(r)
                 }
             },
             Self::Decimal(a) => match $another {
                 Self::Integer(b) => {
                     let r = a $op BigDecimal::from_i32(b).unwrap();
-                    Self::Decimal(r)
+                    Self::from_arith(r)
                 },
                 Self::Decimal(b) => {
-                    Self::Decimal(a $op b)
+                    Self::from_arith(a $op b)
                 }
             }
         }
@@ -239,16 +406,16 @@ macro_rules! complex_op_assign {
                 },
                 Self::Decimal(b) => {
                     let r = BigDecimal::from_i32(*a).unwrap() $op b;
-                    Self::Decimal(r)
+                    Self::from_arith(r)
                 }
             },
             Self::Decimal(a) => match $another {
                 Self::Integer(b) => {
                     let r = a.clone() $op BigDecimal::from_i32(b).unwrap();
-                    Self::Decimal(r)
+                    Self::from_arith(r)
                 },
                 Self::Decimal(b) => {
-                    Self::Decimal(a.clone() $op b)
+                    Self::from_arith(a.clone() $op b)
                 }
             }
         }
@@ -287,6 +454,8 @@ impl ops::SubAssign for Numeric {
     }
 }
 
+// products never overflow, an integer product out of the i32 range
+// becomes a decimal and decimals grow as needed
 impl ops::Mul for Numeric {
     type Output = Numeric;
 
@@ -312,6 +481,34 @@ impl ops::Div for Numeric {
     }
 }
 
+impl Numeric {
+    /// divide, None for a zero divisor
+    pub fn checked_div(self, other: Self) -> Option<Numeric> {
+        if other == Self::ZERO {
+            None
+        } else {
+            Some(self / other)
+        }
+    }
+
+    /// the remainder, None for a zero divisor
+    pub fn checked_rem(self, other: Self) -> Option<Numeric> {
+        if other == Self::ZERO {
+            None
+        } else {
+            Some(self % other)
+        }
+    }
+
+    /// divide and round the quotient half even to at most `precision`
+    /// fractional digits
+    pub fn div_with_precision(self, other: Self, precision: u32) -> Numeric {
+        let v = (self.to_decimal() / other.to_decimal())
+            .with_scale_round(precision as i64, RoundingMode::HalfEven);
+        Self::from_decimal(v.normalized())
+    }
+}
+
 impl ops::DivAssign for Numeric {
     fn div_assign(&mut self, other: Self) {
         *self = Self::Decimal(self.to_decimal() / other.to_decimal());
@@ -382,6 +579,14 @@ impl cmp::Ord for Numeric {
     }
 }
 
+// hash by the decimal value so that equal numbers in different
+// representations, e.g. 1 and 1.0, hash the same
+impl Hash for Numeric {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_decimal().hash(state)
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -405,6 +610,181 @@ mod test {
         let a = super::Numeric::from_str("1.00000000000000000000000000000000005").unwrap();
         let b = super::Numeric::from_str("1.00000000000000000000000000000000008").unwrap();
         let s = a + b;
-        assert_eq!(s.to_string(), "2.0000000000000000000000000000000000"); // the last 13 was stripped
+        assert_eq!(s.to_string(), "2"); // the last 13 was stripped, no trailing zeros are kept
+    }
+
+    #[test]
+    fn test_cross_scale_equality() {
+        use std::cmp::Ordering;
+        let testcases = [("1.0", "1"), ("1.50", "1.5"), ("0", "-0"), ("-0.000", "0")];
+        for (a, b) in testcases {
+            let na = super::Numeric::from_str(a).unwrap();
+            let nb = super::Numeric::from_str(b).unwrap();
+            assert_eq!(na, nb, "{} should equal {}", a, b);
+            assert_eq!(na.cmp(&nb), Ordering::Equal);
+        }
+        let d = super::Numeric::Decimal(bigdecimal::BigDecimal::from(3));
+        assert_eq!(d, super::Numeric::from_i32(3));
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        let a = super::Numeric::from_str("1_000_000").unwrap();
+        assert_eq!(a, super::Numeric::from_i32(1000000));
+        let b = super::Numeric::from_str("1_000.5").unwrap();
+        assert_eq!(b.to_string(), "1000.5");
+        let c = super::Numeric::from_str("0.000_1").unwrap();
+        assert_eq!(c.to_string(), "0.0001");
+
+        for input in ["1__0", "_1", "1_", "1_.5", "1._5"] {
+            assert!(
+                super::Numeric::from_str(input).is_none(),
+                "{} should not parse",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_div_with_precision() {
+        let ten = super::Numeric::from_i32(10);
+        let three = super::Numeric::from_i32(3);
+        assert_eq!(
+            ten.clone().div_with_precision(three.clone(), 5).to_string(),
+            "3.33333"
+        );
+        let two = super::Numeric::from_i32(2);
+        assert_eq!(
+            two.clone().div_with_precision(three, 5).to_string(),
+            "0.66667"
+        );
+        assert_eq!(ten.div_with_precision(two, 5).to_string(), "5");
+    }
+
+    #[test]
+    fn test_to_usize() {
+        let n = |s: &str| super::Numeric::from_str(s).unwrap();
+        assert_eq!(n("0").to_usize(), Some(0));
+        assert_eq!(n("3").to_usize(), Some(3));
+        assert_eq!(n("3.0").to_usize(), Some(3));
+        assert_eq!(n("-1").to_usize(), None);
+        assert_eq!(n("1.5").to_usize(), None);
+        assert_eq!(n("99999999999999999999999").to_usize(), None);
+        assert_eq!(n("99999999999999999999999").to_isize(), None);
+        assert_eq!(n("-2.5").to_isize(), None);
+    }
+
+    #[test]
+    fn test_log_and_exp() {
+        let n = |s: &str| super::Numeric::from_str(s).unwrap();
+        let v = n("8").log(&n("2")).unwrap();
+        assert_eq!(v.with_scale_even(6).to_string(), "3.000000");
+        assert!(n("0").log(&n("2")).is_none());
+        assert!(n("8").log(&n("1")).is_none());
+        assert!(n("8").log(&n("-2")).is_none());
+        assert!(n("1e400").ln().is_none());
+        assert!(n("1e-400").ln().is_none());
+        let e = n("1").exp().unwrap();
+        assert_eq!(e.with_scale_even(6).to_string(), "2.718282");
+        assert!(n("100000").exp().is_none());
+    }
+
+    #[test]
+    fn test_integer_valued_display() {
+        let n = |s: &str| super::Numeric::from_str(s).unwrap();
+        assert_eq!((n("1.5") * n("2")).to_string(), "3");
+        assert_eq!((n("0.5") + n("0.5")).to_string(), "1");
+        assert_eq!((n("1.10") + n("1.90")).to_string(), "3");
+        assert_eq!((n("3.5") - n("1.5")).to_string(), "2");
+        assert_eq!((n("7.5") % n("2.5")).to_string(), "0");
+        assert_eq!((n("6") / n("2")).to_string(), "3");
+        assert_eq!((n("1.5") * n("3000000000")).to_string(), "4500000000");
+        assert_eq!((n("1.25") + n("1.25")).to_string(), "2.50");
+        let mut v = n("0.25");
+        v += n("0.75");
+        assert_eq!(v.to_string(), "1");
+        assert_eq!(n("3").with_scale_even(2).to_string(), "3.00");
+    }
+
+    #[test]
+    fn test_checked_div_rem() {
+        let n = |s: &str| super::Numeric::from_str(s).unwrap();
+        assert!(n("1").checked_div(n("0")).is_none());
+        assert!(n("1.5").checked_div(n("0.0")).is_none());
+        assert!(n("5").checked_rem(n("0")).is_none());
+        assert_eq!(n("7").checked_div(n("2")).unwrap().to_string(), "3.5");
+        assert_eq!(n("7").checked_rem(n("2")).unwrap().to_string(), "1");
+        let big = n("2147483647") * n("2147483647");
+        assert_eq!(big.to_string(), "4611686014132420609");
+    }
+
+    #[test]
+    fn test_pow() {
+        let n = |s: &str| super::Numeric::from_str(s).unwrap();
+        assert_eq!(n("2").pow(&n("10")).unwrap().to_string(), "1024");
+        assert_eq!(n("2").pow(&n("-2")).unwrap().to_string(), "0.25");
+        assert_eq!(n("1.5").pow(&n("2")).unwrap().to_string(), "2.25");
+        assert_eq!(n("-3").pow(&n("3")).unwrap().to_string(), "-27");
+        assert_eq!(n("7").pow(&n("0")).unwrap().to_string(), "1");
+        assert_eq!(
+            n("2").pow(&n("64")).unwrap().to_string(),
+            "18446744073709551616"
+        );
+        let v = n("2").pow(&n("0.5")).unwrap();
+        assert_eq!(v.with_scale_even(6).to_string(), "1.414214");
+        assert!(n("0").pow(&n("-1")).is_none());
+        assert!(n("-8").pow(&n("0.5")).is_none());
+        assert!(n("10").pow(&n("100000")).is_none());
+        // the cap is on the digits of the result rather than the exponent
+        assert_eq!(n("10").pow(&n("1000")).unwrap().to_string().len(), 1001);
+        assert!(n("10").pow(&n("1001")).is_none());
+        assert_eq!(
+            n("1.0001")
+                .pow(&n("100000"))
+                .unwrap()
+                .with_scale_even(6)
+                .to_string(),
+            "22015.456049"
+        );
+        assert_eq!(n("10").pow(&n("-2000")).unwrap().to_string(), "0");
+        // a base below 1 with a negative exponent
+        assert_eq!(
+            n("0.1").pow(&n("-30")).unwrap().to_string(),
+            "1000000000000000000000000000000"
+        );
+        assert_eq!(
+            n("0.1").pow(&n("-80")).unwrap().to_string(),
+            format!("1{}", "0".repeat(80))
+        );
+        assert!(n("0.1").pow(&n("-1001")).is_none());
+        assert_eq!(n("0.5").pow(&n("-3")).unwrap().to_string(), "8");
+        assert_eq!(n("1").pow(&n("-100000000")).unwrap().to_string(), "1");
+    }
+
+    #[test]
+    fn test_fixed_string() {
+        let a = super::Numeric::from_str("1.5").unwrap();
+        assert_eq!(a.to_fixed_string(3), "1.500");
+        assert_eq!(a.to_fixed_string(0), "2");
+
+        let b = super::Numeric::from_str("2.345").unwrap();
+        assert_eq!(b.to_fixed_string(2), "2.34");
+        let c = super::Numeric::from_str("-2.355").unwrap();
+        assert_eq!(c.to_fixed_string(2), "-2.36");
+        assert_eq!(super::Numeric::from_i32(7).to_fixed_string(2), "7.00");
+    }
+
+    #[test]
+    fn test_significant_string() {
+        let a = super::Numeric::from_str("1.5").unwrap();
+        assert_eq!(a.to_significant_string(4), "1.500");
+
+        let b = super::Numeric::from_str("0.0012345").unwrap();
+        assert_eq!(b.to_significant_string(3), "0.00123");
+        assert_eq!(
+            super::Numeric::from_i32(12345).to_significant_string(2),
+            "12000"
+        );
+        assert_eq!(super::Numeric::from_i32(99).to_significant_string(1), "100");
     }
 }
@@ -5,7 +5,7 @@ use std::fmt;
 use std::rc::Rc;
 
 // range
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct RangeT {
     pub start_open: bool,
     pub start: Rc<Value>,
@@ -61,8 +61,20 @@ impl RangeT {
             0
         }
     }
+    /// a range is empty when the start exceeds the end, e.g. [10..1],
+    /// or when the endpoints equal but either side is open
+    pub fn is_empty(&self) -> bool {
+        let r = Self::compare(self.start.as_ref(), self.end.as_ref());
+        r > 0 || (r == 0 && (self.start_open || self.end_open))
+    }
+
     pub fn contains(&self, n: &Value) -> bool {
-        self.position(n) == 0
+        // a point not comparable with the endpoints, e.g. a number
+        // against a string range, is never in the range
+        if n.partial_cmp(&self.start).is_none() || n.partial_cmp(&self.end).is_none() {
+            return false;
+        }
+        !self.is_empty() && self.position(n) == 0
     }
 
     pub fn before_point(&self, p: &Value) -> bool {
@@ -1,3 +1,4 @@
+use super::numeric::Numeric;
 use super::value::Value;
 use super::value::ValueError;
 use crate::eval::{EvalError, EvalErrorKind::*, EvalResult};
@@ -118,6 +119,18 @@ pub(crate) fn today() -> iso8601::Date {
     }
 }
 
+// the milliseconds of the time since midnight at UTC
+pub(crate) fn time_utc_millis(t: &iso8601::Time) -> i64 {
+    let seconds = (t.hour * 3600 + t.minute * 60 + t.second) as i64
+        - (t.tz_offset_hours * 3600 + t.tz_offset_minutes * 60) as i64;
+    seconds * 1000 + t.millisecond as i64
+}
+
+/// times are compared at UTC, e.g. 10:00:00+01:00 equals 09:00:00Z
+pub fn compare_time(a: &iso8601::Time, b: &iso8601::Time) -> cmp::Ordering {
+    compare_value(time_utc_millis(a), time_utc_millis(b))
+}
+
 pub fn compare_date(a: &iso8601::Date, b: &iso8601::Date) -> Option<cmp::Ordering> {
     match *a {
         iso8601::Date::YMD { year, month, day } => match *b {
@@ -146,6 +159,118 @@ pub fn compare_date(a: &iso8601::Date, b: &iso8601::Date) -> Option<cmp::Orderin
     }
 }
 
+// the year-month and the day-time parts of a duration as signed
+// months and milliseconds
+pub(crate) fn duration_parts(dur: &iso8601::Duration, negative: bool) -> (i64, i64) {
+    let (months, millis) = match *dur {
+        iso8601::Duration::YMDHMS {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            millisecond,
+        } => {
+            let secs = ((day as i64 * 24 + hour as i64) * 60 + minute as i64) * 60 + second as i64;
+            (
+                year as i64 * 12 + month as i64,
+                secs * 1000 + millisecond as i64,
+            )
+        }
+        iso8601::Duration::Weeks(w) => (0, w as i64 * 7 * 86400 * 1000),
+    };
+    if negative {
+        (-months, -millis)
+    } else {
+        (months, millis)
+    }
+}
+
+/// the sum of the durations, or the mean when `mean` is set. the
+/// year-month and the day-time parts are added up separately, the
+/// mean truncates them to whole months and milliseconds
+pub(crate) fn aggregate_durations(
+    durations: &[(iso8601::Duration, bool)],
+    mean: bool,
+) -> Result<Value, ValueError> {
+    let overflow = || ValueError("duration out of range".to_owned());
+    let mut months: i64 = 0;
+    let mut millis: i64 = 0;
+    for (dur, negative) in durations.iter() {
+        let (m, ms) = duration_parts(dur, *negative);
+        months = months.checked_add(m).ok_or_else(overflow)?;
+        millis = millis.checked_add(ms).ok_or_else(overflow)?;
+    }
+    if mean && !durations.is_empty() {
+        months /= durations.len() as i64;
+        millis /= durations.len() as i64;
+    }
+    if (months < 0 && millis > 0) || (months > 0 && millis < 0) {
+        return Err(ValueError(
+            "the year-month and day-time parts of the result have different signs".to_owned(),
+        ));
+    }
+    let negative = months < 0 || millis < 0;
+    let (months, millis) = (months.unsigned_abs(), millis.unsigned_abs());
+    let secs = millis / 1000;
+    let to_u32 = |v: u64| u32::try_from(v).map_err(|_| overflow());
+    Ok(Value::DurationV {
+        duration: iso8601::Duration::YMDHMS {
+            year: to_u32(months / 12)?,
+            month: (months % 12) as u32,
+            day: to_u32(secs / 86400)?,
+            hour: (secs / 3600 % 24) as u32,
+            minute: (secs / 60 % 60) as u32,
+            second: (secs % 60) as u32,
+            millisecond: (millis % 1000) as u32,
+        },
+        negative,
+    })
+}
+
+/// a component of a duration as FEEL properties, years and months of
+/// the year-month part, days, hours, minutes and seconds of the
+/// day-time part, e.g. PT25H has 1 day and 1 hour
+pub fn duration_component(dur: &iso8601::Duration, negative: bool, name: &str) -> Option<i64> {
+    let (months, millis) = duration_parts(dur, negative);
+    let secs = millis / 1000;
+    match name {
+        "years" => Some(months / 12),
+        "months" => Some(months % 12),
+        "days" => Some(secs / 86400),
+        "hours" => Some(secs / 3600 % 24),
+        "minutes" => Some(secs / 60 % 60),
+        "seconds" => Some(secs % 60),
+        _ => None,
+    }
+}
+
+/// compare durations by magnitude and sign, e.g. PT1H < PT90M.
+/// year-month durations and day-time durations are not comparable
+/// with each other as the length of a month varies. a duration
+/// without months, including a zero one, is a day-time duration,
+/// e.g. P1M and PT0S are not comparable. durations with both parts
+/// are only comparable when equal
+pub fn compare_duration(
+    a: &iso8601::Duration,
+    a_negative: bool,
+    b: &iso8601::Duration,
+    b_negative: bool,
+) -> Option<cmp::Ordering> {
+    let (a_months, a_millis) = duration_parts(a, a_negative);
+    let (b_months, b_millis) = duration_parts(b, b_negative);
+    if a_months == 0 && b_months == 0 {
+        Some(a_millis.cmp(&b_millis))
+    } else if a_months != 0 && b_months != 0 && a_millis == 0 && b_millis == 0 {
+        Some(a_months.cmp(&b_months))
+    } else if (a_months, a_millis) == (b_months, b_millis) {
+        Some(cmp::Ordering::Equal)
+    } else {
+        None
+    }
+}
+
 pub(crate) fn datetime_op(
     op_is_add: bool,
     cdt: DateTimeT,
@@ -210,13 +335,15 @@ pub(crate) fn datetime_sub(cdt: DateTimeT, dur: iso8601::Duration) -> Result<Dat
     }
 }
 
+// the time delta as a day-time duration, only the sub-second part of
+// the delta goes to the milliseconds
 pub(crate) fn timedelta_to_duration(delta: chrono::TimeDelta) -> (iso8601::Duration, bool) {
     let mut nsecs = delta.num_seconds();
     let negative = nsecs < 0;
     if negative {
         nsecs = -nsecs;
     }
-    let nano = delta.num_nanoseconds().unwrap_or_default().abs();
+    let nano = delta.subsec_nanos().abs() as i64;
 
     let day = nsecs / 86400;
     let hour = (nsecs - day * 86400) / 3600;
@@ -245,6 +372,63 @@ pub(crate) fn date_to_datetime(date: iso8601::Date) -> DateTimeT {
     cdt
 }
 
+// the time offset as a duration, e.g. -PT5H30M for -05:30
+fn offset_duration(offset_secs: i64) -> Value {
+    let secs = offset_secs.unsigned_abs();
+    Value::DurationV {
+        duration: iso8601::Duration::YMDHMS {
+            year: 0,
+            month: 0,
+            day: 0,
+            hour: (secs / 3600) as u32,
+            minute: (secs / 60 % 60) as u32,
+            second: (secs % 60) as u32,
+            millisecond: 0,
+        },
+        negative: offset_secs < 0,
+    }
+}
+
+/// the properties of date, time and date and time values, e.g.
+/// date("2023-06-01").month is 6, None for unknown property names
+pub fn temporal_property(value: &Value, name: &str) -> Option<Value> {
+    use chrono::Timelike;
+    let n = |v: i64| Some(Value::NumberV(Numeric::from_i64(v)));
+    match value {
+        Value::DateV(date) => {
+            let ndate = chrono::NaiveDate::try_from(*date).ok()?;
+            match name {
+                "year" => n(ndate.year() as i64),
+                "month" => n(ndate.month() as i64),
+                "day" => n(ndate.day() as i64),
+                "weekday" => n(ndate.weekday().number_from_monday() as i64),
+                _ => None,
+            }
+        }
+        Value::DateTimeV(cdt) => match name {
+            "year" => n(cdt.year() as i64),
+            "month" => n(cdt.month() as i64),
+            "day" => n(cdt.day() as i64),
+            "weekday" => n(cdt.weekday().number_from_monday() as i64),
+            "hour" => n(cdt.hour() as i64),
+            "minute" => n(cdt.minute() as i64),
+            "second" => n(cdt.second() as i64),
+            "time offset" => Some(offset_duration(cdt.offset().local_minus_utc() as i64)),
+            _ => None,
+        },
+        Value::TimeV(time) => match name {
+            "hour" => n(time.hour as i64),
+            "minute" => n(time.minute as i64),
+            "second" => n(time.second as i64),
+            "time offset" => Some(offset_duration(
+                time.tz_offset_hours as i64 * 3600 + time.tz_offset_minutes as i64 * 60,
+            )),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 pub(crate) fn day_of_week(cdt: DateTimeT) -> &'static str {
     WEEK_NAMES[cdt.weekday().num_days_from_monday() as usize]
 }
@@ -264,6 +448,18 @@ pub(crate) fn install_temporal_prelude(prelude: &mut Prelude) {
         Ok(parse_date(s.as_str())?)
     });
 
+    // strict sibling of date(), the error carries the input text
+    prelude.add_native_func("to date", &["from"], |_, args| -> EvalResult {
+        let arg0 = args.get(&"from".to_owned()).unwrap();
+        let s = arg0.expect_string("argument[1] `from`")?;
+        match iso8601::date(s.as_str()) {
+            Ok(date) => Ok(Value::DateV(date)),
+            Err(_) => Err(EvalError::value_error(
+                format!("fail to parse date from \"{}\"", s).as_str(),
+            )),
+        }
+    });
+
     prelude.add_native_func("time", &["from"], |_, args| -> EvalResult {
         let arg0 = args.get(&"from".to_owned()).unwrap();
         let s = arg0.expect_string("argument[1] `from`")?;
@@ -305,7 +501,7 @@ pub(crate) fn install_temporal_prelude(prelude: &mut Prelude) {
 mod test {
     use super::parse_temporal;
     use crate::values::value::Value;
-    use core::assert_matches::assert_matches;
+    use core::assert_matches;
     extern crate chrono;
     extern crate iso8601;
     use chrono::Datelike;
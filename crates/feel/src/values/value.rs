@@ -1,5 +1,5 @@
 use super::super::ast::Node;
-use super::super::helpers::{compare_value, escape, fmt_vec};
+use super::super::helpers::{compare_value, escape, fmt_vec, write_json_string, StableHasher};
 use core::cell::Ref;
 
 extern crate chrono;
@@ -7,7 +7,10 @@ extern crate iso8601;
 
 use std::cell::RefCell;
 use std::cmp;
+use std::collections::HashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io;
 use std::ops;
 use std::rc::Rc;
 
@@ -15,7 +18,10 @@ use super::context::{Context, ContextRef};
 use super::func::{MacroT, NativeFunc};
 use super::numeric::Numeric;
 use super::range::RangeT;
-use super::temporal::{compare_date, datetime_op, timedelta_to_duration, DateTimeT};
+use super::temporal::{
+    compare_date, compare_duration, compare_time, datetime_op, duration_parts, parse_date,
+    parse_datetime, parse_duration, parse_time, time_utc_millis, timedelta_to_duration, DateTimeT,
+};
 
 // value error
 #[derive(Clone, Debug)]
@@ -59,7 +65,7 @@ pub enum CompareKey {
 
 pub type ArrayRef = Rc<RefCell<Vec<Value>>>;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub enum Value {
     NullV,
     BoolV(bool),
@@ -88,6 +94,8 @@ pub enum Value {
     FuncV {
         func_def: Box<Node>,
         code: String,
+        // variables captured where the function is defined
+        closure: Rc<HashMap<String, Value>>,
     },
 }
 
@@ -115,16 +123,183 @@ impl fmt::Display for Value {
             Self::ArrayV(arr) => fmt_vec(f, arr.borrow().iter(), "[", "]"),
             Self::ContextV(map) => write!(f, "{}", map.borrow()),
             Self::NativeFuncV {
+                required_args,
+                optional_args,
+                var_arg,
+                func,
+            } => {
+                let mut arg_names: Vec<String> = required_args.clone();
+                arg_names.extend(optional_args.iter().map(|a| format!("{}?", a)));
+                if let Some(var_arg_name) = var_arg {
+                    arg_names.push(format!("{}...", var_arg_name));
+                }
+                arg_names.extend(func.named_args.iter().map(|a| format!("{}?", a)));
+                write!(f, "function {}({})", func.name, arg_names.join(", "))
+            }
+            Self::MacroV {
+                required_args,
+                macro_,
+            } => write!(f, "function {}({})", macro_.name, required_args.join(", ")),
+            Self::FuncV {
+                func_def: _,
+                code,
+                closure: _,
+            } => write!(f, "{}", code),
+        }
+    }
+}
+
+// times are equal at UTC, e.g. 10:00:00+01:00 equals 09:00:00Z, and
+// durations are equal when they have the same year-month and day-time
+// parts, e.g. PT1H equals PT60M, the same as they are ordered
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::NullV, Self::NullV) => true,
+            (Self::BoolV(a), Self::BoolV(b)) => a == b,
+            (Self::NumberV(a), Self::NumberV(b)) => a == b,
+            (Self::StrV(a), Self::StrV(b)) => a == b,
+            (Self::DateTimeV(a), Self::DateTimeV(b)) => a == b,
+            (Self::DateV(a), Self::DateV(b)) => a == b,
+            (Self::TimeV(a), Self::TimeV(b)) => time_utc_millis(a) == time_utc_millis(b),
+            (
+                Self::DurationV { duration, negative },
+                Self::DurationV {
+                    duration: b_duration,
+                    negative: b_negative,
+                },
+            ) => duration_parts(duration, *negative) == duration_parts(b_duration, *b_negative),
+            (Self::RangeV(a), Self::RangeV(b)) => a == b,
+            (Self::ArrayV(a), Self::ArrayV(b)) => a == b,
+            (Self::ContextV(a), Self::ContextV(b)) => a == b,
+            (
+                Self::NativeFuncV {
+                    func,
+                    required_args,
+                    optional_args,
+                    var_arg,
+                },
+                Self::NativeFuncV {
+                    func: b_func,
+                    required_args: b_required_args,
+                    optional_args: b_optional_args,
+                    var_arg: b_var_arg,
+                },
+            ) => {
+                func == b_func
+                    && required_args == b_required_args
+                    && optional_args == b_optional_args
+                    && var_arg == b_var_arg
+            }
+            (
+                Self::MacroV {
+                    macro_,
+                    required_args,
+                },
+                Self::MacroV {
+                    macro_: b_macro,
+                    required_args: b_required_args,
+                },
+            ) => macro_ == b_macro && required_args == b_required_args,
+            (
+                Self::FuncV {
+                    func_def,
+                    code,
+                    closure,
+                },
+                Self::FuncV {
+                    func_def: b_func_def,
+                    code: b_code,
+                    closure: b_closure,
+                },
+            ) => func_def == b_func_def && code == b_code && closure == b_closure,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+// structural hash which is consistent with the equality, iso8601
+// values don't implement Hash so they are hashed by their string
+// form or, for times and durations, their normalized parts, functions are hashed by name
+// or source code
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data_type().hash(state);
+        match self {
+            Self::NullV => (),
+            Self::BoolV(v) => v.hash(state),
+            Self::NumberV(v) => v.hash(state),
+            Self::StrV(v) => v.hash(state),
+            Self::DateTimeV(v) => v.hash(state),
+            Self::DateV(v) => v.to_string().hash(state),
+            Self::TimeV(v) => time_utc_millis(v).hash(state),
+            Self::DurationV { duration, negative } => {
+                duration_parts(duration, *negative).hash(state)
+            }
+            Self::RangeV(v) => v.hash(state),
+            Self::ArrayV(arr) => arr.borrow().hash(state),
+            Self::ContextV(ctx) => ctx.borrow().hash(state),
+            Self::NativeFuncV {
+                func,
                 required_args: _,
                 optional_args: _,
                 var_arg: _,
-                func: _,
-            } => write!(f, "{}", "function"),
+            } => func.name.hash(state),
             Self::MacroV {
+                macro_,
                 required_args: _,
-                macro_: _,
-            } => write!(f, "{}", "function"),
-            Self::FuncV { func_def: _, code } => write!(f, "{}", code),
+            } => macro_.name.hash(state),
+            Self::FuncV {
+                func_def: _,
+                code,
+                closure: _,
+            } => code.hash(state),
+        }
+    }
+}
+
+/// the types a value can be coerced to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueType {
+    Number,
+    String,
+    Boolean,
+    Date,
+    Time,
+    DateTime,
+    Duration,
+    List,
+}
+
+impl ValueType {
+    /// the type of the name, e.g. "number" or "date and time"
+    pub fn from_name(name: &str) -> Option<ValueType> {
+        match name {
+            "number" => Some(Self::Number),
+            "string" => Some(Self::String),
+            "boolean" => Some(Self::Boolean),
+            "date" => Some(Self::Date),
+            "time" => Some(Self::Time),
+            "date time" | "date and time" => Some(Self::DateTime),
+            "duration" => Some(Self::Duration),
+            "list" | "array" => Some(Self::List),
+            _ => None,
+        }
+    }
+
+    /// the name agrees with `Value::data_type`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Boolean => "boolean",
+            Self::Date => "date",
+            Self::Time => "time",
+            Self::DateTime => "date time",
+            Self::Duration => "duration",
+            Self::List => "array",
         }
     }
 }
@@ -167,10 +342,326 @@ impl Value {
             Self::FuncV {
                 func_def: _,
                 code: _,
+                closure: _,
             } => "function".to_owned(),
         }
     }
 
+    /// the type with the element types of arrays and the entry types
+    /// of contexts, e.g. `list<number>`, `list<any>` for empty or mixed
+    /// arrays, and `context<a: number, b: string>`. structures nested
+    /// deeper than a limit are reported by their data type only
+    pub fn type_descriptor(&self) -> String {
+        self.type_descriptor_at(0)
+    }
+
+    /// the nesting depth of lists and contexts, scalars are 0 and
+    /// `[{a: 1}]` is 2. the walk stops as soon as the depth is known to
+    /// pass `limit`, the result is then `limit + 1`
+    pub fn nesting_depth(&self, limit: usize) -> usize {
+        let children: Vec<Value> = match self {
+            Self::ArrayV(arr) => arr.borrow().clone(),
+            Self::ContextV(ctx) => ctx.borrow().0.values().cloned().collect(),
+            _ => return 0,
+        };
+        if limit == 0 {
+            return 1;
+        }
+        let mut depth = 0;
+        for v in children.iter() {
+            depth = depth.max(v.nesting_depth(limit - 1));
+            if depth >= limit {
+                break;
+            }
+        }
+        depth + 1
+    }
+
+    /// rebuild the lists and contexts with `f` applied to the other
+    /// values, e.g. to redact the strings of a result. a list or
+    /// context shared by several parents is mapped once and stays
+    /// shared, one containing itself is kept as is where it recurs
+    pub fn map_leaves(&self, f: &dyn Fn(&Value) -> Value) -> Value {
+        self.map_leaves_with(f, &mut HashMap::new(), &mut vec![])
+    }
+
+    fn map_leaves_with(
+        &self,
+        f: &dyn Fn(&Value) -> Value,
+        mapped: &mut HashMap<*const (), Value>,
+        ancestors: &mut Vec<*const ()>,
+    ) -> Value {
+        let ptr = match self {
+            Self::ArrayV(arr) => Rc::as_ptr(arr) as *const (),
+            Self::ContextV(ctx) => Rc::as_ptr(ctx) as *const (),
+            _ => return f(self),
+        };
+        if let Some(v) = mapped.get(&ptr) {
+            return v.clone();
+        }
+        if ancestors.contains(&ptr) {
+            return self.clone();
+        }
+        ancestors.push(ptr);
+        let res = match self {
+            Self::ArrayV(arr) => {
+                let items: Vec<Value> = arr
+                    .borrow()
+                    .iter()
+                    .map(|v| v.map_leaves_with(f, mapped, ancestors))
+                    .collect();
+                Self::ArrayV(Rc::new(RefCell::new(items)))
+            }
+            Self::ContextV(ctx) => {
+                let mut res_ctx = Context::new();
+                for (k, v) in ctx.borrow().0.iter() {
+                    res_ctx.insert(k.clone(), v.map_leaves_with(f, mapped, ancestors));
+                }
+                Self::ContextV(Rc::new(RefCell::new(res_ctx)))
+            }
+            _ => unreachable!(),
+        };
+        ancestors.pop();
+        mapped.insert(ptr, res.clone());
+        res
+    }
+
+    fn type_descriptor_at(&self, depth: usize) -> String {
+        const MAX_DEPTH: usize = 16;
+        match self {
+            Self::ArrayV(arr) if depth < MAX_DEPTH => {
+                let mut elem_type: Option<String> = None;
+                for v in arr.borrow().iter() {
+                    let t = v.type_descriptor_at(depth + 1);
+                    match elem_type {
+                        None => elem_type = Some(t),
+                        Some(ref et) if *et == t => (),
+                        _ => return "list<any>".to_owned(),
+                    }
+                }
+                format!("list<{}>", elem_type.unwrap_or("any".to_owned()))
+            }
+            Self::ArrayV(_) => "list".to_owned(),
+            Self::ContextV(ctx) if depth < MAX_DEPTH => {
+                let entries: Vec<String> = ctx
+                    .borrow()
+                    .0
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k, v.type_descriptor_at(depth + 1)))
+                    .collect();
+                format!("context<{}>", entries.join(", "))
+            }
+            Self::ContextV(_) => "context".to_owned(),
+            _ => self.data_type(),
+        }
+    }
+
+    /// whether the value can be an endpoint of a range, which
+    /// requires the type to be ordered
+    pub fn is_range_endpoint(&self) -> bool {
+        matches!(
+            self,
+            Self::NumberV(_)
+                | Self::StrV(_)
+                | Self::DateTimeV(_)
+                | Self::DateV(_)
+                | Self::TimeV(_)
+                | Self::DurationV { .. }
+        )
+    }
+
+    /// whether the value is a scalar whose hash is cheap and agrees
+    /// with the equality, so that it can be a key of hash sets
+    pub fn is_hashable_scalar(&self) -> bool {
+        match self {
+            Self::NullV | Self::BoolV(_) => true,
+            _ => self.is_range_endpoint(),
+        }
+    }
+
+    pub fn is_function(&self) -> bool {
+        matches!(
+            self,
+            Self::NativeFuncV { .. } | Self::MacroV { .. } | Self::FuncV { .. }
+        )
+    }
+
+    /// structural equality, arrays are equal when they have equal
+    /// elements in the same order and contexts are equal when they have
+    /// the same keys with equal values, whether or not they share the
+    /// same storage. numbers are compared by value, e.g. 1.0 equals 1.
+    /// the derived `==` follows the same contract
+    pub fn deep_equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Self::ArrayV(a), Self::ArrayV(b)) => {
+                if Rc::ptr_eq(a, b) {
+                    return true;
+                }
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.deep_equals(y))
+            }
+            (Self::ContextV(a), Self::ContextV(b)) => {
+                if Rc::ptr_eq(a, b) {
+                    return true;
+                }
+                let (a, b) = (a.borrow().entries(), b.borrow().entries());
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|((ka, va), (kb, vb))| ka == kb && va.deep_equals(vb))
+            }
+            _ => self == other,
+        }
+    }
+
+    /// parse a JSON document into values, objects become contexts
+    /// and arrays become lists. the error carries the parser's line
+    /// and column
+    pub fn from_json_str(input: &str) -> Result<Value, ValueError> {
+        match serde_json::from_str::<serde_json::Value>(input) {
+            Ok(json) => Ok(Self::from_json(json)),
+            Err(err) => Err(ValueError(format!("fail to parse json: {}", err))),
+        }
+    }
+
+    fn from_json(json: serde_json::Value) -> Value {
+        match json {
+            serde_json::Value::Null => Self::NullV,
+            serde_json::Value::Bool(v) => Self::BoolV(v),
+            serde_json::Value::Number(v) => match Numeric::from_str(&v.to_string()) {
+                Some(n) => Self::NumberV(n),
+                None => Self::NullV,
+            },
+            serde_json::Value::String(v) => Self::StrV(v),
+            serde_json::Value::Array(items) => {
+                let arr: Vec<Value> = items.into_iter().map(Self::from_json).collect();
+                Self::ArrayV(Rc::new(RefCell::new(arr)))
+            }
+            serde_json::Value::Object(entries) => {
+                let mut ctx = Context::new();
+                for (k, v) in entries.into_iter() {
+                    ctx.insert(k, Self::from_json(v));
+                }
+                Self::ContextV(Rc::new(RefCell::new(ctx)))
+            }
+        }
+    }
+
+    /// stream the value as JSON to the writer without building the
+    /// whole string in memory. temporal values are written as ISO
+    /// strings, ranges as {"start", "end", "start included", "end
+    /// included"} objects, functions cannot be written
+    pub fn write_json<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_json_indent(writer, None, 0)
+    }
+
+    /// like write_json() but indent nested lists and contexts by two
+    /// spaces a level
+    pub fn write_json_pretty<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_json_indent(writer, Some(2), 0)
+    }
+
+    /// the value as a JSON string, optionally indented
+    pub fn to_json_string(&self, pretty: bool) -> Result<String, ValueError> {
+        let mut buf: Vec<u8> = vec![];
+        let res = if pretty {
+            self.write_json_pretty(&mut buf)
+        } else {
+            self.write_json(&mut buf)
+        };
+        match res {
+            Ok(_) => Ok(String::from_utf8_lossy(&buf).into_owned()),
+            Err(err) => Err(ValueError(err.to_string())),
+        }
+    }
+
+    fn write_json_indent<W: io::Write>(
+        &self,
+        writer: &mut W,
+        indent: Option<usize>,
+        level: usize,
+    ) -> io::Result<()> {
+        // the separator before an item, a newline and the indent of
+        // the level when pretty printing
+        let write_break = |writer: &mut W, level: usize| -> io::Result<()> {
+            match indent {
+                Some(n) => write!(writer, "\n{:width$}", "", width = n * level),
+                None => Ok(()),
+            }
+        };
+        let key_sep = if indent.is_some() { ": " } else { ":" };
+        match self {
+            Self::NullV => writer.write_all(b"null"),
+            Self::BoolV(v) => write!(writer, "{}", v),
+            Self::NumberV(v) => write!(writer, "{}", v),
+            Self::StrV(v) => write_json_string(writer, v),
+            Self::DateTimeV(v) => {
+                write_json_string(writer, &v.format("%Y-%m-%dT%H:%M:%S%:z").to_string())
+            }
+            Self::DateV(v) => write_json_string(writer, &v.to_string()),
+            Self::TimeV(v) => write_json_string(writer, &v.to_string()),
+            Self::DurationV { duration, negative } => {
+                let sign = if *negative { "-" } else { "" };
+                write_json_string(writer, &format!("{}{}", sign, duration))
+            }
+            Self::RangeV(v) => {
+                let mut ctx = Context::new();
+                ctx.insert("start".to_owned(), v.start.as_ref().clone());
+                ctx.insert("end".to_owned(), v.end.as_ref().clone());
+                ctx.insert("start included".to_owned(), Self::BoolV(!v.start_open));
+                ctx.insert("end included".to_owned(), Self::BoolV(!v.end_open));
+                Self::ContextV(Rc::new(RefCell::new(ctx))).write_json_indent(writer, indent, level)
+            }
+            Self::ArrayV(arr) => {
+                let arr = arr.borrow();
+                if arr.is_empty() {
+                    return writer.write_all(b"[]");
+                }
+                writer.write_all(b"[")?;
+                for (i, v) in arr.iter().enumerate() {
+                    if i > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    write_break(writer, level + 1)?;
+                    v.write_json_indent(writer, indent, level + 1)?;
+                }
+                write_break(writer, level)?;
+                writer.write_all(b"]")
+            }
+            Self::ContextV(ctx) => {
+                let ctx = ctx.borrow();
+                if ctx.0.is_empty() {
+                    return writer.write_all(b"{}");
+                }
+                writer.write_all(b"{")?;
+                for (i, (k, v)) in ctx.0.iter().enumerate() {
+                    if i > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    write_break(writer, level + 1)?;
+                    write_json_string(writer, k)?;
+                    writer.write_all(key_sep.as_bytes())?;
+                    v.write_json_indent(writer, indent, level + 1)?;
+                }
+                write_break(writer, level)?;
+                writer.write_all(b"}")
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} cannot be written as json", self.data_type()),
+            )),
+        }
+    }
+
+    /// a hash of the value which stays the same across runs, equal
+    /// values always have the same hash, e.g. contexts with the same
+    /// entries regardless of the order
+    pub fn stable_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
     pub fn bool_value(&self) -> bool {
         match self {
             Self::NullV => false,
@@ -192,6 +683,43 @@ impl Value {
         }
     }
 
+    /// convert the value to the type, a value of the type is kept as
+    /// it is, strings are parsed into numbers, booleans and temporal
+    /// values, scalars are formatted into strings and a non-list value
+    /// is wrapped into a singleton list
+    pub fn coerce_to(&self, value_type: ValueType) -> ValueResult {
+        if self.data_type() == value_type.name() {
+            return Ok(self.clone());
+        }
+        match (value_type, self) {
+            (ValueType::Number, Self::StrV(_)) => Ok(Self::NumberV(self.parse_number()?)),
+            (ValueType::String, Self::NumberV(_) | Self::BoolV(_)) => {
+                Ok(Self::StrV(self.to_string()))
+            }
+            (ValueType::String, Self::DateTimeV(v)) => {
+                Ok(Self::StrV(v.format("%Y-%m-%dT%H:%M:%S%:z").to_string()))
+            }
+            (ValueType::String, Self::DateV(v)) => Ok(Self::StrV(v.to_string())),
+            (ValueType::String, Self::TimeV(v)) => Ok(Self::StrV(v.to_string())),
+            (ValueType::String, Self::DurationV { duration, negative }) => {
+                let sign = if *negative { "-" } else { "" };
+                Ok(Self::StrV(format!("{}{}", sign, duration)))
+            }
+            (ValueType::Boolean, Self::StrV(s)) if s == "true" => Ok(Self::BoolV(true)),
+            (ValueType::Boolean, Self::StrV(s)) if s == "false" => Ok(Self::BoolV(false)),
+            (ValueType::Date, Self::StrV(s)) => parse_date(s),
+            (ValueType::Time, Self::StrV(s)) => parse_time(s),
+            (ValueType::DateTime, Self::StrV(s)) => parse_datetime(s),
+            (ValueType::Duration, Self::StrV(s)) => parse_duration(s),
+            (ValueType::List, _) => Ok(Self::ArrayV(Rc::new(RefCell::new(vec![self.clone()])))),
+            _ => Err(ValueError(format!(
+                "cannot coerce {} to {}",
+                self.data_type(),
+                value_type.name()
+            ))),
+        }
+    }
+
     pub fn parse_number(&self) -> Result<Numeric, ValueError> {
         match self {
             Self::StrV(s) => Numeric::from_str(s)
@@ -226,7 +754,10 @@ impl Value {
     pub fn expect_integer(&self, hint: &str) -> Result<isize, ValueError> {
         if let Self::NumberV(n) = self {
             if n.is_integer() {
-                return Ok(n.to_isize().unwrap());
+                return n.to_isize().ok_or(ValueError(format!(
+                    "{}, expect integer, but {} is out of range",
+                    hint, n
+                )));
             }
         }
         Err(ValueError(format!(
@@ -240,7 +771,10 @@ impl Value {
         if let Self::NumberV(n) = self {
             if n.is_integer() {
                 if n.is_sign_positive() {
-                    return Ok(n.to_usize().unwrap());
+                    return n.to_usize().ok_or(ValueError(format!(
+                        "{}, expect possitive integer, but {} is out of range",
+                        hint, n
+                    )));
                 } else {
                     return Err(ValueError(format!(
                         "{}, expect possitive integer, but negative found",
@@ -441,7 +975,10 @@ impl ops::Div for Value {
     fn div(self, other: Self) -> Self::Output {
         match self {
             Self::NumberV(a) => match other {
-                Self::NumberV(b) => Ok(Self::NumberV(a / b)),
+                Self::NumberV(b) => match a.checked_div(b) {
+                    Some(v) => Ok(Self::NumberV(v)),
+                    None => Err(ValueError("division by zero".to_owned())),
+                },
                 _ => Err(ValueError(format!(
                     "canot / number and {}",
                     other.data_type()
@@ -463,7 +1000,10 @@ impl ops::Rem for Value {
     fn rem(self, other: Self) -> Self::Output {
         match self {
             Self::NumberV(a) => match other {
-                Self::NumberV(b) => Ok(Self::NumberV(a % b)),
+                Self::NumberV(b) => match a.checked_rem(b) {
+                    Some(v) => Ok(Self::NumberV(v)),
+                    None => Err(ValueError("division by zero".to_owned())),
+                },
                 _ => Err(ValueError(format!(
                     "canot % number and {}",
                     other.data_type()
@@ -499,6 +1039,15 @@ impl ops::Not for Value {
     }
 }
 
+impl Value {
+    /// whether `=` and `!=` are defined between the values. values of
+    /// different types, e.g. 1 and "1", are not comparable, while null
+    /// is comparable with any value
+    pub fn comparable_with(&self, other: &Value) -> bool {
+        *self == Self::NullV || *other == Self::NullV || self.data_type() == other.data_type()
+    }
+}
+
 impl cmp::PartialOrd for Value {
     #[inline(always)]
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
@@ -519,6 +1068,17 @@ impl cmp::PartialOrd for Value {
                 Self::DateV(b) => compare_date(a, b),
                 _ => None,
             },
+            Self::TimeV(a) => match other {
+                Self::TimeV(b) => Some(compare_time(a, b)),
+                _ => None,
+            },
+            Self::DurationV { duration, negative } => match other {
+                Self::DurationV {
+                    duration: b_duration,
+                    negative: b_negative,
+                } => compare_duration(duration, *negative, b_duration, *b_negative),
+                _ => None,
+            },
             _ => None,
         }
     }